@@ -1,36 +1,232 @@
+// Generated from the parser's Rust types, do not edit by hand.
+
+export interface Breakdowns {
+  types_per_color: Record<string, FacetValue[]>;
+  sets_per_card_type: Record<string, FacetValue[]>;
+}
+
 export interface Card {
   card_name: string;
+  name_key: string;
   card_number: string;
-  rarity: string;
+  rarity: Rarity;
   is_alternate_art: boolean;
-  card_type: string;
+  card_type: CardType;
+  /** From the image URL templates in data/image-urls.json, the official image by default */
   image_url: string;
+  /** The image on the official site, as linked from the card list */
+  official_image_url: string;
   life: string;
   cost: string;
   attributes: string[];
   power: string;
   counter: string;
-  colors: string[];
+  block_icon: string;
+  /** Block icon of the newest printing, which decides rotation */
+  effective_block_icon: string;
+  colors: Color[];
   types: string[];
-  effects: string;
+  effects: string | null;
   card_effects: string[];
+  referenced_types: string[];
+  referenced_names: string[];
+  referenced_card_numbers: string[];
+  referenced_attributes: string[];
+  counter_value: number | null;
+  is_counter_event: boolean;
+  counter_power_boost: number | null;
+  trigger: string | null;
+  has_trigger: boolean;
+  trigger_details: TriggerDetails | null;
+  legality: Legality;
+  /** Ids of the formats in data/formats.json this card is legal in */
+  legal_formats: string[];
   card_sets: string;
   image_name: string;
+  /** Sizes made by the `derivatives` command, from the image URL templates */
+  images?: CardImages;
+}
+
+/** Where each size of a card's image is, from the `images` URL template */
+export interface CardImages {
+  thumb: string;
+  medium: string;
+  full: string;
+}
+
+export type CardType = "LEADER" | "STAGE" | "EVENT" | "CHARACTER" | "DON!!" | string;
+
+export type Color = "Red" | "Blue" | "Green" | "Yellow" | "Black" | "Purple";
+
+export type FacetKey = number | string | boolean;
+
+export interface FacetValue {
+  value: FacetKey;
+  count: number;
+}
+
+export interface Facets {
+  rarities: FacetValue[];
+  card_types: FacetValue[];
+  colors: FacetValue[];
+  life_values: FacetValue[];
+  cost_values: FacetValue[];
+  powers: FacetValue[];
+  counters: FacetValue[];
+  block_icons: FacetValue[];
+  attributes: FacetValue[];
+  types: FacetValue[];
+  card_effects: FacetValue[];
+  card_sets: FacetValue[];
+  has_trigger: FacetValue[];
 }
 
 export interface Filters {
-  colors: string[];
-  rarities: string[];
+  colors: Color[];
+  card_names: string[];
+  card_numbers: string[];
+  rarities: Rarity[];
+  card_types: CardType[];
+  life_values: string[];
+  cost_values: string[];
+  powers: string[];
+  counters: string[];
+  block_icons: string[];
+  attributes: string[];
   types: string[];
-  sets: string[];
-  categories: string[];
+  card_effects: string[];
+  card_sets: string[];
+  triggers: string[];
+  has_trigger: boolean[];
+  facets: Facets;
+  ranges: Ranges;
+  breakdowns: Breakdowns;
+}
+
+export interface IndexDocument {
+  card_number: string;
+  card_name: string;
+}
+
+export interface IndexField {
+  name: string;
+  /** Score multiplier for matches in this field */
+  weight: number;
+}
+
+/** Where a card stands on the ban list in effect when the data was written */
+export interface Legality {
+  status: LegalityStatus;
+  /** Copies allowed in a deck, 0 when banned */
+  max_copies: number;
+  /** Card numbers this card can't share a deck with */
+  banned_with: string[];
+  /** Effective date of the ban list this comes from, null before the first one */
+  ban_list_date: string | null;
+}
+
+export type LegalityStatus = "legal" | "restricted" | "banned";
+
+/** Written next to cards.json by every scrape */
+export interface Meta {
+  /** YYYY-MM-DD, `legality` and `legal_formats` are as of this day */
+  scraped_at: string;
 }
 
+export interface NumericRange {
+  min: number;
+  max: number;
+}
+
+/** `[document, field, occurrences]` */
+export type Posting = [number, number, number];
+
+export interface Ranges {
+  life: NumericRange | null;
+  cost: NumericRange | null;
+  power: NumericRange | null;
+  counter: NumericRange | null;
+}
+
+export type Rarity = "C" | "UC" | "R" | "SR" | "L" | "SP CARD" | "SEC" | "P" | "TR" | "-";
+
+/** Prebuilt full-text index over the card names, types and effect text, one document per card number. Written next to cards.json so clients can search without building their own. */
+export interface SearchIndex {
+  /** Indexed fields, referenced by position from `Posting` */
+  fields: IndexField[];
+  /** Referenced by position from `Posting` */
+  documents: IndexDocument[];
+  /** Normalized term to every document containing it */
+  terms: Record<string, Posting[]>;
+}
+
+export interface TriggerDetails {
+  cost: string | null;
+  effect: string;
+  plays_this_card: boolean;
+  activates: string | null;
+}
+
+/** Type specific view of a card, only the fields that mean something for that card type are present and numbers are actual numbers instead of "-" */
+export type TypedCard = {
+  card_name: string;
+  name_key: string;
+  card_number: string;
+  rarity: Rarity;
+  is_alternate_art: boolean;
+  image_url: string;
+  official_image_url: string;
+  block_icon: string;
+  effective_block_icon: string;
+  colors: Color[];
+  types: string[];
+  effects: string | null;
+  card_effects: string[];
+  referenced_types: string[];
+  referenced_names: string[];
+  referenced_card_numbers: string[];
+  referenced_attributes: string[];
+  trigger: string | null;
+  has_trigger: boolean;
+  trigger_details: TriggerDetails | null;
+  legality: Legality;
+  legal_formats: string[];
+  card_sets: string;
+  image_name: string;
+  images?: CardImages;
+} & ({
+  card_type: "LEADER";
+  life: number | null;
+  power: number | null;
+  attributes: string[];
+} | {
+  card_type: "CHARACTER";
+  cost: number | null;
+  power: number | null;
+  counter: number | null;
+  attributes: string[];
+} | {
+  card_type: "EVENT";
+  cost: number | null;
+  is_counter_event: boolean;
+  counter_power_boost: number | null;
+} | {
+  card_type: "STAGE";
+  cost: number | null;
+} | {
+  card_type: "DON!!";
+} | {
+  card_type: string;
+});
+
 declare module 'one-piece-card-game-json' {
   export const all: Card[];
-  export const jp: Card[];
   export const en: {
     cards: Card[];
     filters: Filters;
   };
-}
\ No newline at end of file
+  export const jp: {
+    cards: Card[];
+    filters: Filters;
+  };
+}
@@ -0,0 +1,268 @@
+use serde::Serialize;
+use std::{fs, collections::BTreeMap, path::Path};
+
+use crate::{is_sentinel, label, parse_number, set_key, sha256_hex, slugify, synergy, unique_cards, Card, CardType, Color};
+
+pub const API_VERSION: &str = "v1";
+pub const DEFAULT_PER_PAGE: usize = 100;
+
+#[derive(Debug, Serialize)]
+struct Meta<'a> {
+    version: &'static str,
+    region: &'a str,
+    card_count: usize,
+    unique_card_count: usize,
+    set_count: usize,
+    type_count: usize,
+    leader_count: usize,
+    per_page: usize,
+    total_pages: usize,
+    schema_version: u32,
+    dataset_sha256: String,
+}
+
+#[derive(Debug, Serialize)]
+struct CardEntry<'a> {
+    card_number: &'a str,
+    card_name: &'a str,
+    printings: Vec<&'a Card>,
+}
+
+#[derive(Debug, Serialize)]
+struct Page<'a> {
+    page: usize,
+    per_page: usize,
+    total: usize,
+    total_pages: usize,
+    prev: Option<String>,
+    next: Option<String>,
+    data: &'a [&'a Card],
+}
+
+// Keyed by slug, with the display name and every card in the group
+type Groups<'a> = BTreeMap<String, (&'a str, Vec<&'a Card>)>;
+
+#[derive(Debug, Serialize)]
+struct Group<'a> {
+    key: String,
+    name: &'a str,
+    card_count: usize,
+    cards: Vec<&'a Card>,
+}
+
+#[derive(Debug, Serialize)]
+struct GroupSummary<'a> {
+    key: &'a str,
+    name: &'a str,
+    card_count: usize,
+    path: String,
+}
+
+// Just enough for a client side search box, the full card is one fetch away
+#[derive(Debug, Serialize)]
+struct SearchEntry<'a> {
+    card_number: &'a str,
+    card_name: &'a str,
+    card_type: String,
+    colors: &'a [Color],
+    cost: Option<u32>,
+    power: Option<u32>,
+    types: &'a [String],
+    card_sets: &'a str,
+    path: String,
+}
+
+/// Writes the static API tree under `output_dir/v1`, from the same cards `save_output` writes
+pub fn generate(cards: &[Card], region: &str, output_dir: &str, per_page: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let root = Path::new(output_dir).join(API_VERSION);
+    if root.exists() {
+        fs::remove_dir_all(&root)?;
+    }
+
+    let per_page = per_page.max(1);
+
+    // Every printing, grouped by card number
+    let mut by_number: BTreeMap<&str, Vec<&Card>> = BTreeMap::new();
+    for card in cards {
+        by_number.entry(&card.card_number).or_default().push(card);
+    }
+
+    for (card_number, printings) in &by_number {
+        write_json(&root, &card_path(card_number), &CardEntry {
+            card_number,
+            card_name: &printings[0].card_name,
+            printings: printings.clone(),
+        })?;
+    }
+
+    // Paginated list of every printing
+    let all: Vec<&Card> = cards.iter().collect();
+    let total_pages = all.len().div_ceil(per_page).max(1);
+    for page in 1..=total_pages {
+        let start = (page - 1) * per_page;
+        let data = &all[start.min(all.len())..(start + per_page).min(all.len())];
+        write_json(&root, &page_path(page), &Page {
+            page,
+            per_page,
+            total: all.len(),
+            total_pages,
+            prev: (page > 1).then(|| page_path(page - 1)),
+            next: (page < total_pages).then(|| page_path(page + 1)),
+            data,
+        })?;
+    }
+
+    let mut sets: Groups = BTreeMap::new();
+    let mut types: Groups = BTreeMap::new();
+    for card in cards {
+        if let Some(key) = set_key(&card.card_sets) {
+            sets.entry(key).or_insert((&card.card_sets, Vec::new())).1.push(card);
+        }
+        for card_type in card.types.iter().filter(|value| !is_sentinel(value)) {
+            let key = slugify(card_type);
+            if !key.is_empty() {
+                types.entry(key).or_insert((card_type, Vec::new())).1.push(card);
+            }
+        }
+    }
+
+    let set_summaries = write_groups(&root, "sets", &sets)?;
+    write_json(&root, "sets.json", &set_summaries)?;
+
+    let type_summaries = write_groups(&root, "types", &types)?;
+    write_json(&root, "types.json", &type_summaries)?;
+
+    let leaders: Vec<&Card> = unique_cards(cards)
+        .into_iter()
+        .filter(|card| card.card_type == CardType::LEADER)
+        .collect();
+    write_json(&root, "leaders.json", &leaders)?;
+
+    // Color matching cards ranked by trait and name synergy, per Leader
+    for report in synergy::leader_reports(cards) {
+        write_json(&root, &format!("leaders/{}.json", report.card_number), &report)?;
+    }
+
+    let search_index: Vec<SearchEntry> = unique_cards(cards)
+        .into_iter()
+        .map(|card| SearchEntry {
+            card_number: &card.card_number,
+            card_name: &card.card_name,
+            card_type: label(&card.card_type),
+            colors: &card.colors,
+            cost: parse_number(&card.cost),
+            power: parse_number(&card.power),
+            types: &card.types,
+            card_sets: &card.card_sets,
+            path: card_path(&card.card_number),
+        })
+        .collect();
+    write_json(&root, "search-index.json", &search_index)?;
+
+    write_json(&root, "meta.json", &Meta {
+        version: API_VERSION,
+        region,
+        card_count: cards.len(),
+        unique_card_count: by_number.len(),
+        set_count: sets.len(),
+        type_count: types.len(),
+        leader_count: leaders.len(),
+        per_page,
+        total_pages,
+        schema_version: crate::schema::SCHEMA_VERSION,
+        dataset_sha256: sha256_hex(serde_json::to_string(cards)?.as_bytes()),
+    })?;
+
+    Ok(())
+}
+
+fn card_path(card_number: &str) -> String {
+    format!("cards/{}.json", card_number)
+}
+
+fn page_path(page: usize) -> String {
+    format!("cards/page/{}.json", page)
+}
+
+fn write_groups<'a>(root: &Path, dir: &str, groups: &'a Groups<'a>) -> Result<Vec<GroupSummary<'a>>, Box<dyn std::error::Error>> {
+    let mut summaries = Vec::new();
+
+    for (key, (name, group_cards)) in groups {
+        let path = format!("{}/{}.json", dir, key);
+        write_json(root, &path, &Group {
+            key: key.clone(),
+            name,
+            card_count: group_cards.len(),
+            cards: group_cards.clone(),
+        })?;
+
+        summaries.push(GroupSummary {
+            key,
+            name,
+            card_count: group_cards.len(),
+            path,
+        });
+    }
+
+    Ok(summaries)
+}
+
+fn write_json<T: Serialize>(root: &Path, path: &str, value: &T) -> Result<(), Box<dyn std::error::Error>> {
+    let path = root.join(path);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(value)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_card;
+    use serde_json::{json, Value};
+
+    fn read_json(root: &Path, path: &str) -> Value {
+        serde_json::from_str(&fs::read_to_string(root.join(path)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn writes_index_cards_and_sets() {
+        let dir = std::env::temp_dir().join(format!("api-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let cards = vec![
+            test_card(json!({ "card_number": "OP01-001", "card_name": "Roronoa Zoro", "card_type": "LEADER", "life": "5", "counter": "-" })),
+            test_card(json!({ "card_number": "OP02-004", "card_name": "Edward.Newgate", "card_sets": "-PARAMOUNT WAR- [OP02]", "types": ["Whitebeard Pirates"] })),
+        ];
+        generate(&cards, "en", dir.to_str().unwrap(), 1).unwrap();
+        let root = dir.join(API_VERSION);
+
+        let meta = read_json(&root, "meta.json");
+        assert_eq!(meta["region"], "en");
+        assert_eq!(
+            (&meta["card_count"], &meta["unique_card_count"], &meta["set_count"], &meta["leader_count"], &meta["total_pages"]),
+            (&json!(2), &json!(2), &json!(2), &json!(1), &json!(2))
+        );
+
+        let search_index = read_json(&root, "search-index.json");
+        let paths: Vec<&str> = search_index.as_array().unwrap().iter().map(|entry| entry["path"].as_str().unwrap()).collect();
+        assert_eq!(paths, ["cards/OP01-001.json", "cards/OP02-004.json"]);
+
+        let newgate = read_json(&root, "cards/OP02-004.json");
+        assert_eq!(newgate["card_name"], "Edward.Newgate");
+        assert_eq!(newgate["printings"][0]["card_number"], "OP02-004");
+
+        let first_page = read_json(&root, "cards/page/1.json");
+        assert_eq!((&first_page["next"], &first_page["prev"]), (&json!("cards/page/2.json"), &Value::Null));
+
+        let sets = read_json(&root, "sets.json");
+        let set_paths: Vec<&str> = sets.as_array().unwrap().iter().map(|set| set["path"].as_str().unwrap()).collect();
+        assert_eq!(set_paths, ["sets/paramount-war-op02.json", "sets/romance-dawn-op01.json"]);
+
+        let romance_dawn = read_json(&root, "sets/romance-dawn-op01.json");
+        assert_eq!(romance_dawn["name"], "-ROMANCE DAWN- [OP01]");
+        assert_eq!(romance_dawn["card_count"], 1);
+        assert_eq!(romance_dawn["cards"][0]["card_name"], "Roronoa Zoro");
+    }
+}
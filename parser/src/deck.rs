@@ -0,0 +1,384 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, HashMap}, fmt};
+
+mod formats;
+mod stats;
+
+pub use formats::DeckFormat;
+
+use crate::{label, legality::{legality, BanListUpdate, LegalityStatus}, rotation::Format, unique_cards, Card, CardType, Color};
+
+pub const DECK_SIZE: u32 = 50;
+pub const MAX_COPIES: u32 = 4;
+
+/// A Leader plus the 50 card main deck, by card number
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deck {
+    pub leader: String,
+    pub cards: Vec<DeckEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeckEntry {
+    pub card_number: String,
+    pub count: u32,
+}
+
+/// What a deck is checked against on top of the construction rules
+#[derive(Debug, Default)]
+pub struct Regulation<'a> {
+    /// The ban list in effect on `date`
+    pub ban_list: Option<&'a BanListUpdate>,
+    pub format: Option<&'a Format>,
+    /// YYYY-MM-DD, for the format's rotation
+    pub date: String,
+}
+
+/// A broken deck construction rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum Violation {
+    UnknownCard { card_number: String },
+    NotALeader { card_number: String },
+    DeckSize { count: u32, expected: u32 },
+    TooManyCopies { card_number: String, count: u32, max: u32 },
+    LeaderInMainDeck { card_number: String },
+    DonInMainDeck { card_number: String },
+    ColorMismatch { card_number: String, colors: Vec<Color>, leader_colors: Vec<Color> },
+    Banned { card_number: String, ban_list_date: String },
+    Restricted { card_number: String, count: u32, max: u32, ban_list_date: String },
+    BannedPair { card_numbers: [String; 2], ban_list_date: String },
+    RotatedOut { card_number: String, block_icon: String, format: String },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let colors = |colors: &[Color]| colors.iter().map(label).collect::<Vec<_>>().join("/");
+
+        match self {
+            Violation::UnknownCard { card_number } => write!(f, "{} is not a known card", card_number),
+            Violation::NotALeader { card_number } => write!(f, "{} is not a Leader card", card_number),
+            Violation::DeckSize { count, expected } => write!(f, "The main deck has {} cards, it needs exactly {}", count, expected),
+            Violation::TooManyCopies { card_number, count, max } => write!(f, "{} has {} copies, at most {} are allowed", card_number, count, max),
+            Violation::LeaderInMainDeck { card_number } => write!(f, "{} is a Leader and can't be in the main deck", card_number),
+            Violation::DonInMainDeck { card_number } => write!(f, "{} is a DON!! card and can't be in the main deck", card_number),
+            Violation::ColorMismatch { card_number, colors: card_colors, leader_colors } => write!(
+                f,
+                "{} is {}, which doesn't match the {} Leader",
+                card_number,
+                colors(card_colors),
+                colors(leader_colors),
+            ),
+            Violation::Banned { card_number, ban_list_date } => write!(f, "{} is banned (ban list of {})", card_number, ban_list_date),
+            Violation::Restricted { card_number, count, max, ban_list_date } => write!(
+                f,
+                "{} has {} copies, it's restricted to {} (ban list of {})",
+                card_number,
+                count,
+                max,
+                ban_list_date,
+            ),
+            Violation::BannedPair { card_numbers: [a, b], ban_list_date } => write!(
+                f,
+                "{} and {} can't be in the same deck (ban list of {})",
+                a,
+                b,
+                ban_list_date,
+            ),
+            Violation::RotatedOut { card_number, block_icon, format } => write!(
+                f,
+                "{} is from block {}, which isn't legal in {}",
+                card_number,
+                block_icon,
+                format,
+            ),
+        }
+    }
+}
+
+impl Deck {
+    /// Total cards in the main deck
+    pub fn card_count(&self) -> u32 {
+        self.cards.iter().map(|entry| entry.count).sum()
+    }
+
+    /// Copies per card number, with repeated entries added together
+    pub fn copies(&self) -> BTreeMap<&str, u32> {
+        let mut copies = BTreeMap::new();
+        for entry in &self.cards {
+            *copies.entry(entry.card_number.as_str()).or_default() += entry.count;
+        }
+        copies
+    }
+
+    /// Checks the deck against the construction rules and `regulation`, using
+    /// `cards` to look up each card number. An empty list means the deck is legal.
+    pub fn validate(&self, cards: &[Card], regulation: &Regulation) -> Vec<Violation> {
+        let ban_list = regulation.ban_list;
+        let pool: HashMap<&str, &Card> = unique_cards(cards)
+            .into_iter()
+            .map(|card| (card.card_number.as_str(), card))
+            .collect();
+
+        let mut violations = Vec::new();
+
+        let leader = match pool.get(self.leader.as_str()) {
+            None => {
+                violations.push(Violation::UnknownCard { card_number: self.leader.clone() });
+                None
+            }
+            Some(card) if card.card_type != CardType::LEADER => {
+                violations.push(Violation::NotALeader { card_number: self.leader.clone() });
+                None
+            }
+            Some(card) => Some(*card),
+        };
+
+        let count = self.card_count();
+        if count != DECK_SIZE {
+            violations.push(Violation::DeckSize { count, expected: DECK_SIZE });
+        }
+
+        for (card_number, count) in self.copies() {
+            let Some(card) = pool.get(card_number) else {
+                violations.push(Violation::UnknownCard { card_number: card_number.to_string() });
+                continue;
+            };
+
+            match card.card_type {
+                CardType::LEADER => violations.push(Violation::LeaderInMainDeck { card_number: card_number.to_string() }),
+                CardType::DON => violations.push(Violation::DonInMainDeck { card_number: card_number.to_string() }),
+                _ => {}
+            }
+
+            let card_legality = legality(ban_list, card_number);
+            let ban_list_date = card_legality.ban_list_date.clone().unwrap_or_default();
+            match card_legality.status {
+                LegalityStatus::Banned => violations.push(Violation::Banned { card_number: card_number.to_string(), ban_list_date }),
+                LegalityStatus::Restricted if count > card_legality.max_copies => violations.push(Violation::Restricted {
+                    card_number: card_number.to_string(),
+                    count,
+                    max: card_legality.max_copies,
+                    ban_list_date,
+                }),
+                _ if count > MAX_COPIES => violations.push(Violation::TooManyCopies { card_number: card_number.to_string(), count, max: MAX_COPIES }),
+                _ => {}
+            }
+
+            // Multicolor cards only need to share one color with the Leader
+            if let Some(leader) = leader {
+                if !card.colors.iter().any(|color| leader.colors.contains(color)) {
+                    violations.push(Violation::ColorMismatch {
+                        card_number: card_number.to_string(),
+                        colors: card.colors.clone(),
+                        leader_colors: leader.colors.clone(),
+                    });
+                }
+            }
+        }
+
+        if let Some(format) = regulation.format {
+            for card_number in self.copies().into_keys().chain([self.leader.as_str()]) {
+                let Some(card) = pool.get(card_number) else {
+                    continue;
+                };
+
+                if !format.allows(&card.effective_block_icon, &regulation.date) {
+                    violations.push(Violation::RotatedOut {
+                        card_number: card_number.to_string(),
+                        block_icon: card.effective_block_icon.clone(),
+                        format: format.name.clone(),
+                    });
+                }
+            }
+        }
+
+        if let Some(ban_list) = ban_list {
+            let in_deck: Vec<&str> = self.copies().into_keys().chain([self.leader.as_str()]).collect();
+
+            if ban_list.banned.contains(&self.leader) {
+                violations.push(Violation::Banned { card_number: self.leader.clone(), ban_list_date: ban_list.effective_date.clone() });
+            }
+
+            for [a, b] in &ban_list.banned_pairs {
+                if in_deck.contains(&a.as_str()) && in_deck.contains(&b.as_str()) {
+                    violations.push(Violation::BannedPair {
+                        card_numbers: [a.clone(), b.clone()],
+                        ban_list_date: ban_list.effective_date.clone(),
+                    });
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{legality::Restriction, test_card};
+    use serde_json::json;
+
+    // A red Leader, a red/green Leader, 13 red cards OP01-040 to OP01-052,
+    // a green card, a red/green card and a DON!! card
+    fn cards() -> Vec<Card> {
+        let mut cards = vec![
+            test_card(json!({ "card_number": "OP01-001", "card_type": "LEADER", "colors": ["Red"] })),
+            test_card(json!({ "card_number": "OP01-002", "card_type": "LEADER", "colors": ["Red", "Green"] })),
+            test_card(json!({ "card_number": "OP01-031", "colors": ["Green"] })),
+            test_card(json!({ "card_number": "OP01-032", "colors": ["Red", "Green"] })),
+            test_card(json!({ "card_number": "DON-001", "card_type": "DON!!", "colors": [] })),
+        ];
+        cards.extend((40..=52).map(|number| test_card(json!({ "card_number": format!("OP01-{:03}", number), "colors": ["Red"] }))));
+        cards
+    }
+
+    // 4 copies each of the first `distinct` red cards
+    fn red_deck(leader: &str, distinct: u32, extra: &[(&str, u32)]) -> Deck {
+        let cards = (40..40 + distinct)
+            .map(|number| (format!("OP01-{:03}", number), 4))
+            .chain(extra.iter().map(|(card_number, count)| (card_number.to_string(), *count)))
+            .map(|(card_number, count)| DeckEntry { card_number, count })
+            .collect();
+
+        Deck { leader: leader.to_string(), cards }
+    }
+
+    fn validate(deck: &Deck) -> Vec<Violation> {
+        deck.validate(&cards(), &Regulation::default())
+    }
+
+    #[test]
+    fn legal_deck_has_no_violations() {
+        assert_eq!(validate(&red_deck("OP01-001", 12, &[("OP01-052", 2)])), vec![]);
+        // Multicolor cards only need one color in common
+        assert_eq!(validate(&red_deck("OP01-001", 12, &[("OP01-032", 2)])), vec![]);
+        assert_eq!(validate(&red_deck("OP01-002", 12, &[("OP01-031", 2)])), vec![]);
+    }
+
+    #[test]
+    fn deck_size() {
+        assert_eq!(validate(&red_deck("OP01-001", 12, &[])), vec![Violation::DeckSize { count: 48, expected: DECK_SIZE }]);
+        assert_eq!(validate(&red_deck("OP01-001", 13, &[])), vec![Violation::DeckSize { count: 52, expected: DECK_SIZE }]);
+    }
+
+    #[test]
+    fn repeated_entries_add_up_to_too_many_copies() {
+        let deck = red_deck("OP01-001", 12, &[("OP01-040", 2)]);
+        assert_eq!(deck.copies()["OP01-040"], 6);
+        assert_eq!(validate(&deck), vec![Violation::TooManyCopies { card_number: "OP01-040".to_string(), count: 6, max: MAX_COPIES }]);
+    }
+
+    #[test]
+    fn leader_and_main_deck_card_types() {
+        assert_eq!(validate(&red_deck("OP01-040", 12, &[("OP01-052", 2)])), vec![Violation::NotALeader { card_number: "OP01-040".to_string() }]);
+        assert_eq!(validate(&red_deck("OP01-001", 12, &[("OP01-002", 1), ("DON-001", 1)])), vec![
+            Violation::DonInMainDeck { card_number: "DON-001".to_string() },
+            Violation::ColorMismatch { card_number: "DON-001".to_string(), colors: vec![], leader_colors: vec![Color::Red] },
+            Violation::LeaderInMainDeck { card_number: "OP01-002".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn colors_have_to_match_the_leader() {
+        assert_eq!(validate(&red_deck("OP01-001", 12, &[("OP01-031", 2)])), vec![Violation::ColorMismatch {
+            card_number: "OP01-031".to_string(),
+            colors: vec![Color::Green],
+            leader_colors: vec![Color::Red],
+        }]);
+    }
+
+    #[test]
+    fn unknown_cards() {
+        assert_eq!(validate(&red_deck("OP99-001", 12, &[("OP99-002", 2)])), vec![
+            Violation::UnknownCard { card_number: "OP99-001".to_string() },
+            Violation::UnknownCard { card_number: "OP99-002".to_string() },
+        ]);
+    }
+
+    fn ban_list() -> BanListUpdate {
+        BanListUpdate {
+            effective_date: "2025-01-01".to_string(),
+            banned: vec!["OP01-041".to_string(), "OP01-002".to_string()],
+            restricted: vec![Restriction { card_number: "OP01-042".to_string(), max_copies: 1 }],
+            banned_pairs: vec![["OP01-001".to_string(), "OP01-043".to_string()]],
+        }
+    }
+
+    fn validate_with_ban_list(deck: &Deck) -> Vec<Violation> {
+        let ban_list = ban_list();
+        deck.validate(&cards(), &Regulation { ban_list: Some(&ban_list), ..Regulation::default() })
+    }
+
+    #[test]
+    fn banned_and_restricted_cards() {
+        let deck = Deck {
+            leader: "OP01-002".to_string(),
+            cards: vec![
+                DeckEntry { card_number: "OP01-041".to_string(), count: 1 },
+                DeckEntry { card_number: "OP01-042".to_string(), count: 2 },
+                DeckEntry { card_number: "OP01-044".to_string(), count: 4 },
+            ],
+        };
+        let ban_list_date = "2025-01-01".to_string();
+
+        assert_eq!(validate_with_ban_list(&deck), vec![
+            Violation::DeckSize { count: 7, expected: DECK_SIZE },
+            Violation::Banned { card_number: "OP01-041".to_string(), ban_list_date: ban_list_date.clone() },
+            Violation::Restricted { card_number: "OP01-042".to_string(), count: 2, max: 1, ban_list_date: ban_list_date.clone() },
+            // The Leader is banned too
+            Violation::Banned { card_number: "OP01-002".to_string(), ban_list_date },
+        ]);
+    }
+
+    #[test]
+    fn restricted_cards_within_their_limit_are_fine() {
+        let cards = ["OP01-040", "OP01-044", "OP01-045", "OP01-046", "OP01-047", "OP01-048", "OP01-049", "OP01-050", "OP01-051", "OP01-052"]
+            .into_iter()
+            .map(|card_number| DeckEntry { card_number: card_number.to_string(), count: 4 })
+            .chain([DeckEntry { card_number: "OP01-042".to_string(), count: 1 }])
+            .collect();
+        let deck = Deck { leader: "OP01-001".to_string(), cards };
+
+        // Too few cards are left to fill the deck without the banned ones
+        assert_eq!(validate_with_ban_list(&deck), vec![Violation::DeckSize { count: 41, expected: DECK_SIZE }]);
+    }
+
+    #[test]
+    fn banned_pairs_only_count_together() {
+        let ban_list_date = "2025-01-01".to_string();
+        let pair = Violation::BannedPair { card_numbers: ["OP01-001".to_string(), "OP01-043".to_string()], ban_list_date };
+
+        // Counts as a pair with the Leader as well as with main deck cards
+        let with_pair = red_deck("OP01-001", 12, &[("OP01-052", 2)]);
+        assert!(validate_with_ban_list(&with_pair).contains(&pair));
+
+        let without_pair = Deck { leader: "OP01-002".to_string(), ..with_pair };
+        assert!(!validate_with_ban_list(&without_pair).contains(&pair));
+    }
+
+    #[test]
+    fn rotated_out_cards() {
+        let mut cards = cards();
+        for card in cards.iter_mut().filter(|card| card.card_number == "OP01-052") {
+            card.effective_block_icon = "1".to_string();
+        }
+        let standard: Format = serde_json::from_value(json!({
+            "id": "standard",
+            "name": "Standard",
+            "rotations": [{ "effective_date": "2026-04-01", "rotated_blocks": ["1"] }],
+        })).unwrap();
+        let deck = red_deck("OP01-001", 12, &[("OP01-052", 2)]);
+
+        let before = Regulation { format: Some(&standard), date: "2026-03-31".to_string(), ..Regulation::default() };
+        assert_eq!(deck.validate(&cards, &before), vec![]);
+
+        let after = Regulation { format: Some(&standard), date: "2026-04-01".to_string(), ..Regulation::default() };
+        assert_eq!(deck.validate(&cards, &after), vec![Violation::RotatedOut {
+            card_number: "OP01-052".to_string(),
+            block_icon: "1".to_string(),
+            format: "Standard".to_string(),
+        }]);
+    }
+}
@@ -0,0 +1,350 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use clap::ValueEnum;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{Deck, DeckEntry};
+use crate::{unique_cards, Card, CardType};
+
+// First byte of every deck code, bump it if the layout below changes
+const DECK_CODE_VERSION: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DeckFormat {
+    /// One `4xOP01-016` per line, with the Leader as `1xOP01-001`
+    Text,
+    /// `{"leader": "OP01-001", "cards": [{"card_number": "OP01-016", "count": 4}]}`
+    Json,
+    /// Compact URL-safe base64 code for sharing in links
+    Code,
+}
+
+impl DeckFormat {
+    /// Guesses the format of a deck list from its content
+    pub fn detect(input: &str) -> DeckFormat {
+        let input = input.trim();
+        let text_line = Regex::new(r"^\d+\s*[xX]?\s*[A-Za-z]+\d*-\d+").unwrap();
+
+        if input.starts_with('{') {
+            DeckFormat::Json
+        } else if input.lines().map(str::trim).any(|line| text_line.is_match(line)) {
+            DeckFormat::Text
+        } else {
+            DeckFormat::Code
+        }
+    }
+
+    /// Reads a deck, every card number has to be in `cards`
+    pub fn parse(&self, input: &str, cards: &[Card]) -> Result<Deck, Box<dyn std::error::Error>> {
+        let pool = CardPool::new(cards);
+
+        match self {
+            DeckFormat::Text => parse_text(input, &pool),
+            DeckFormat::Json => {
+                let deck: Deck = serde_json::from_str(input)?;
+                pool.check(deck)
+            }
+            DeckFormat::Code => pool.check(decode(input.trim())?),
+        }
+    }
+
+    pub fn write(&self, deck: &Deck) -> Result<String, Box<dyn std::error::Error>> {
+        match self {
+            DeckFormat::Text => {
+                let mut text = format!("1x{}\n", deck.leader);
+                for entry in &deck.cards {
+                    text.push_str(&format!("{}x{}\n", entry.count, entry.card_number));
+                }
+                Ok(text)
+            }
+            DeckFormat::Json => Ok(serde_json::to_string_pretty(deck)?),
+            DeckFormat::Code => encode(deck),
+        }
+    }
+}
+
+// Card numbers people type can be lowercase, the dataset's are not
+struct CardPool<'a> {
+    cards: HashMap<String, &'a Card>,
+}
+
+impl<'a> CardPool<'a> {
+    fn new(cards: &'a [Card]) -> Self {
+        CardPool {
+            cards: unique_cards(cards)
+                .into_iter()
+                .map(|card| (card.card_number.to_uppercase(), card))
+                .collect(),
+        }
+    }
+
+    fn get(&self, card_number: &str) -> Result<&'a Card, String> {
+        self.cards
+            .get(&card_number.trim().to_uppercase())
+            .copied()
+            .ok_or_else(|| format!("Unknown card number: {}", card_number.trim()))
+    }
+
+    // Swaps every card number for the dataset's spelling of it
+    fn check(&self, deck: Deck) -> Result<Deck, Box<dyn std::error::Error>> {
+        let mut entries = Vec::new();
+        for entry in deck.cards {
+            entries.push(DeckEntry {
+                card_number: self.get(&entry.card_number)?.card_number.clone(),
+                count: entry.count,
+            });
+        }
+
+        Ok(Deck {
+            leader: self.get(&deck.leader)?.card_number.clone(),
+            cards: entries,
+        })
+    }
+}
+
+fn parse_text(input: &str, pool: &CardPool) -> Result<Deck, Box<dyn std::error::Error>> {
+    let line_pattern = Regex::new(r"^(\d+)\s*[xX]?\s*([A-Za-z]+\d*-\d+)").unwrap();
+
+    let mut leader = None;
+    let mut entries = Vec::new();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            continue;
+        }
+
+        let captures = line_pattern.captures(line)
+            .ok_or_else(|| format!("Line {}: expected something like 4xOP01-016, got {}", index + 1, line))?;
+        let count: u32 = captures[1].parse()?;
+        let card = pool.get(&captures[2]).map_err(|err| format!("Line {}: {}", index + 1, err))?;
+
+        // The first single Leader is the deck's Leader, any others are mistakes for the validator
+        if leader.is_none() && card.card_type == CardType::LEADER && count == 1 {
+            leader = Some(card.card_number.clone());
+        } else {
+            entries.push(DeckEntry { card_number: card.card_number.clone(), count });
+        }
+    }
+
+    Ok(Deck {
+        leader: leader.ok_or("No Leader in the deck list, add it as 1x<card number>")?,
+        cards: entries,
+    })
+}
+
+// Version byte, the Leader, then the main deck grouped by card number prefix
+// (OP01, ST10, P, ...): prefix, entry count, then copies and number per entry.
+// Prefixes are length prefixed ASCII and numbers are LEB128 varints.
+fn encode(deck: &Deck) -> Result<String, Box<dyn std::error::Error>> {
+    let mut bytes = vec![DECK_CODE_VERSION];
+
+    let (prefix, number) = split_card_number(&deck.leader)?;
+    write_prefix(&mut bytes, prefix)?;
+    write_varint(&mut bytes, number);
+
+    let mut groups: BTreeMap<&str, Vec<(u32, u32)>> = BTreeMap::new();
+    for entry in &deck.cards {
+        let (prefix, number) = split_card_number(&entry.card_number)?;
+        groups.entry(prefix).or_default().push((entry.count, number));
+    }
+
+    for (prefix, entries) in groups {
+        write_prefix(&mut bytes, prefix)?;
+        write_varint(&mut bytes, entries.len() as u32);
+        for (count, number) in entries {
+            write_varint(&mut bytes, count);
+            write_varint(&mut bytes, number);
+        }
+    }
+
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn decode(code: &str) -> Result<Deck, Box<dyn std::error::Error>> {
+    let bytes = URL_SAFE_NO_PAD.decode(code).map_err(|err| format!("Invalid deck code: {}", err))?;
+    let mut reader = bytes.iter().copied();
+
+    match reader.next() {
+        Some(DECK_CODE_VERSION) => {}
+        Some(version) => return Err(format!("Unsupported deck code version: {}", version).into()),
+        None => return Err("Empty deck code".into()),
+    }
+
+    let prefix = read_prefix(&mut reader)?.ok_or("Deck code has no Leader")?;
+    let leader = card_number(&prefix, read_varint(&mut reader)?);
+
+    let mut cards = Vec::new();
+    while let Some(prefix) = read_prefix(&mut reader)? {
+        let entry_count = read_varint(&mut reader)?;
+        for _ in 0..entry_count {
+            let count = read_varint(&mut reader)?;
+            let number = read_varint(&mut reader)?;
+            cards.push(DeckEntry { card_number: card_number(&prefix, number), count });
+        }
+    }
+
+    Ok(Deck { leader, cards })
+}
+
+fn split_card_number(card_number: &str) -> Result<(&str, u32), Box<dyn std::error::Error>> {
+    card_number
+        .split_once('-')
+        .and_then(|(prefix, number)| Some((prefix, number.parse().ok()?)))
+        .filter(|(prefix, _)| !prefix.is_empty() && prefix.len() <= u8::MAX as usize)
+        .ok_or_else(|| format!("Can't put {} in a deck code", card_number).into())
+}
+
+fn card_number(prefix: &str, number: u32) -> String {
+    format!("{}-{:03}", prefix, number)
+}
+
+fn write_prefix(bytes: &mut Vec<u8>, prefix: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if !prefix.is_ascii() {
+        return Err(format!("Can't put {} in a deck code", prefix).into());
+    }
+    bytes.push(prefix.len() as u8);
+    bytes.extend(prefix.bytes());
+    Ok(())
+}
+
+// None at the end of the code
+fn read_prefix(reader: &mut impl Iterator<Item = u8>) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let Some(length) = reader.next() else {
+        return Ok(None);
+    };
+
+    let prefix: Vec<u8> = reader.take(length as usize).collect();
+    if prefix.len() != length as usize {
+        return Err("Deck code ends in the middle of a card number".into());
+    }
+
+    Ok(Some(String::from_utf8(prefix)?))
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(reader: &mut impl Iterator<Item = u8>) -> Result<u32, Box<dyn std::error::Error>> {
+    let mut value: u32 = 0;
+    for shift in (0..32).step_by(7) {
+        let byte = reader.next().ok_or("Deck code ends in the middle of a number")?;
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Invalid number in deck code".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_card;
+    use serde_json::json;
+
+    fn cards() -> Vec<Card> {
+        vec![
+            test_card(json!({ "card_number": "OP01-001", "card_type": "LEADER" })),
+            test_card(json!({ "card_number": "OP01-016" })),
+            test_card(json!({ "card_number": "OP01-025" })),
+            test_card(json!({ "card_number": "ST01-012" })),
+            test_card(json!({ "card_number": "P-001" })),
+            test_card(json!({ "card_number": "OP05-119" })),
+        ]
+    }
+
+    fn deck() -> Deck {
+        Deck {
+            leader: "OP01-001".to_string(),
+            cards: [("OP01-016", 4), ("OP01-025", 2), ("ST01-012", 4), ("P-001", 1), ("OP05-119", 3)]
+                .into_iter()
+                .map(|(card_number, count)| DeckEntry { card_number: card_number.to_string(), count })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn every_format_roundtrips() {
+        for format in [DeckFormat::Text, DeckFormat::Json, DeckFormat::Code] {
+            let written = format.write(&deck()).unwrap();
+            assert_eq!(DeckFormat::detect(&written), format, "{}", written);
+
+            let mut parsed = format.parse(&written, &cards()).unwrap();
+            // The code groups entries by prefix
+            parsed.cards.sort_by(|a, b| a.card_number.cmp(&b.card_number));
+            let mut expected = deck();
+            expected.cards.sort_by(|a, b| a.card_number.cmp(&b.card_number));
+            assert_eq!(parsed, expected, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn code_survives_large_numbers() {
+        let deck = Deck {
+            leader: "OP01-001".to_string(),
+            cards: vec![DeckEntry { card_number: "OP01-300".to_string(), count: 200 }],
+        };
+        assert_eq!(decode(&encode(&deck).unwrap()).unwrap(), deck);
+    }
+
+    #[test]
+    fn malformed_codes_are_errors() {
+        let code = encode(&deck()).unwrap();
+        let bytes = URL_SAFE_NO_PAD.decode(&code).unwrap();
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = DECK_CODE_VERSION + 1;
+
+        for code in [
+            String::new(),
+            "not a code!".to_string(),
+            URL_SAFE_NO_PAD.encode(&bytes[..bytes.len() - 1]),
+            URL_SAFE_NO_PAD.encode(&bytes[..3]),
+            URL_SAFE_NO_PAD.encode([DECK_CODE_VERSION]),
+            URL_SAFE_NO_PAD.encode(wrong_version),
+        ] {
+            assert!(decode(&code).is_err(), "{:?}", code);
+        }
+    }
+
+    #[test]
+    fn empty_input_has_no_leader() {
+        for format in [DeckFormat::Text, DeckFormat::Code] {
+            assert!(format.parse("", &cards()).is_err(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn text_takes_spaced_counts_and_comments() {
+        let input = "\
+            # Red Zoro\n\
+            // from the store championship\n\
+            1x OP01-001\n\
+            \n\
+            4 x OP01-016\n\
+            2x op01-025\n\
+            4 ST01-012\n\
+            1X P-001\n\
+            3 x OP05-119 Roronoa Zoro\n";
+
+        assert_eq!(DeckFormat::detect(input), DeckFormat::Text);
+        assert_eq!(DeckFormat::Text.parse(input, &cards()).unwrap(), deck());
+    }
+
+    #[test]
+    fn text_errors_name_the_line() {
+        let error = DeckFormat::Text.parse("1xOP01-001\n4xOP01-016\nfour of OP01-025", &cards()).unwrap_err();
+        assert!(error.to_string().starts_with("Line 3:"), "{}", error);
+
+        let error = DeckFormat::Text.parse("1xOP01-001\n4xOP99-001", &cards()).unwrap_err();
+        assert_eq!(error.to_string(), "Line 2: Unknown card number: OP99-001");
+
+        assert!(DeckFormat::Text.parse("4xOP01-016", &cards()).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use serde::Serialize;
+use std::{collections::{BTreeMap, HashMap}, fmt};
+
+use super::{Deck, DECK_SIZE};
+use crate::{is_sentinel, label, parse_number, unique_cards, Card, CardType};
+
+/// Cards in the opening hand
+pub const OPENING_HAND: u32 = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeckStats {
+    pub leader: String,
+    pub card_count: u32,
+    /// Copies per cost, for cards with a cost
+    pub cost_curve: BTreeMap<u32, u32>,
+    pub average_cost: Option<f64>,
+    /// Copies per power, for Characters
+    pub power_distribution: BTreeMap<u32, u32>,
+    pub counters: CounterStats,
+    /// Copies with a [Trigger]
+    pub trigger_count: u32,
+    pub card_types: BTreeMap<String, u32>,
+    pub traits: BTreeMap<String, u32>,
+    /// Copies per bracketed keyword, e.g. Blocker or Rush
+    pub keywords: BTreeMap<String, u32>,
+    /// Odds of drawing each card number
+    pub odds: Vec<CardOdds>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CounterStats {
+    /// Copies per Character counter value, e.g. 1000 and 2000
+    pub by_value: BTreeMap<u32, u32>,
+    /// Characters without a counter
+    pub no_counter: u32,
+    /// Sum of every Character counter in the deck
+    pub total: u32,
+    /// Copies of [Counter] Events
+    pub counter_events: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CardOdds {
+    pub card_number: String,
+    pub card_name: String,
+    pub copies: u32,
+    /// Chance of at least one copy in the opening hand
+    pub opening_hand: f64,
+    /// Same, when mulliganing every hand without it
+    pub with_mulligan: f64,
+}
+
+impl Deck {
+    /// Stats for the main deck, every card number has to be in `cards`
+    pub fn stats(&self, cards: &[Card]) -> Result<DeckStats, Box<dyn std::error::Error>> {
+        let pool: HashMap<&str, &Card> = unique_cards(cards)
+            .into_iter()
+            .map(|card| (card.card_number.as_str(), card))
+            .collect();
+        let lookup = |card_number: &str| pool.get(card_number).copied().ok_or_else(|| format!("Unknown card number: {}", card_number));
+
+        let mut stats = DeckStats {
+            leader: lookup(&self.leader)?.card_number.clone(),
+            card_count: self.card_count(),
+            cost_curve: BTreeMap::new(),
+            average_cost: None,
+            power_distribution: BTreeMap::new(),
+            counters: CounterStats::default(),
+            trigger_count: 0,
+            card_types: BTreeMap::new(),
+            traits: BTreeMap::new(),
+            keywords: BTreeMap::new(),
+            odds: Vec::new(),
+        };
+
+        let mut cost_total = 0;
+        let mut costed_cards = 0;
+        // Odds are over the real deck size, so a short list doesn't look better than it is
+        let deck_size = stats.card_count.max(DECK_SIZE);
+
+        for (card_number, copies) in self.copies() {
+            let card = lookup(card_number)?;
+
+            if let Some(cost) = parse_number(&card.cost) {
+                *stats.cost_curve.entry(cost).or_default() += copies;
+                cost_total += cost * copies;
+                costed_cards += copies;
+            }
+
+            if card.card_type == CardType::CHARACTER {
+                if let Some(power) = parse_number(&card.power) {
+                    *stats.power_distribution.entry(power).or_default() += copies;
+                }
+
+                match card.counter_value.filter(|value| *value > 0) {
+                    Some(value) => {
+                        *stats.counters.by_value.entry(value).or_default() += copies;
+                        stats.counters.total += value * copies;
+                    }
+                    None => stats.counters.no_counter += copies,
+                }
+            }
+
+            if card.is_counter_event {
+                stats.counters.counter_events += copies;
+            }
+
+            if card.has_trigger {
+                stats.trigger_count += copies;
+            }
+
+            *stats.card_types.entry(label(&card.card_type)).or_default() += copies;
+
+            for card_trait in card.types.iter().filter(|value| !is_sentinel(value)) {
+                *stats.traits.entry(card_trait.clone()).or_default() += copies;
+            }
+
+            let keywords: Vec<&str> = card.card_effects.iter()
+                .filter(|value| !is_sentinel(value))
+                .map(|effect| effect.trim_matches(['[', ']']))
+                .collect();
+            for keyword in keywords {
+                *stats.keywords.entry(keyword.to_string()).or_default() += copies;
+            }
+        }
+
+        if costed_cards > 0 {
+            stats.average_cost = Some(cost_total as f64 / costed_cards as f64);
+        }
+
+        for (card_number, copies) in self.copies() {
+            let card = lookup(card_number)?;
+            let opening_hand = chance_to_draw(deck_size, copies, OPENING_HAND);
+
+            stats.odds.push(CardOdds {
+                card_number: card.card_number.clone(),
+                card_name: card.card_name.clone(),
+                copies,
+                opening_hand,
+                with_mulligan: chance_with_mulligan(opening_hand),
+            });
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Chance of at least one of `copies` in `drawn` cards from a `deck_size` deck
+pub fn chance_to_draw(deck_size: u32, copies: u32, drawn: u32) -> f64 {
+    if copies == 0 || drawn == 0 {
+        return 0.0;
+    }
+    if copies > deck_size.saturating_sub(drawn) {
+        return 1.0;
+    }
+
+    // 1 - C(deck - copies, drawn) / C(deck, drawn), as a running product
+    let miss: f64 = (0..drawn)
+        .map(|i| (deck_size - copies - i) as f64 / (deck_size - i) as f64)
+        .product();
+    1.0 - miss
+}
+
+/// The one mulligan shuffles the whole hand back, so a miss gets a fresh try
+pub fn chance_with_mulligan(opening_hand: f64) -> f64 {
+    opening_hand + (1.0 - opening_hand) * opening_hand
+}
+
+impl fmt::Display for DeckStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |counts: &BTreeMap<String, u32>| {
+            let mut counts: Vec<(&String, &u32)> = counts.iter().collect();
+            counts.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            counts.iter().map(|(name, count)| format!("{} {}", name, count)).collect::<Vec<_>>().join(", ")
+        };
+
+        writeln!(f, "Leader: {}", self.leader)?;
+        writeln!(f, "Cards: {}", self.card_count)?;
+        if let Some(average_cost) = self.average_cost {
+            writeln!(f, "Average cost: {:.2}", average_cost)?;
+        }
+
+        writeln!(f, "\nCost curve")?;
+        for (cost, count) in &self.cost_curve {
+            writeln!(f, "  {:>2}  {:<20} {}", cost, "#".repeat(*count as usize), count)?;
+        }
+
+        writeln!(f, "\nPower")?;
+        for (power, count) in &self.power_distribution {
+            writeln!(f, "  {:>5}  {}", power, count)?;
+        }
+
+        let counters: Vec<String> = self.counters.by_value.iter().map(|(value, count)| format!("{} x{}", value, count)).collect();
+        writeln!(f, "\nCounters: {} (total {}), no counter {}, counter events {}",
+            counters.join(", "), self.counters.total, self.counters.no_counter, self.counters.counter_events)?;
+        writeln!(f, "Triggers: {}", self.trigger_count)?;
+        writeln!(f, "Card types: {}", list(&self.card_types))?;
+        writeln!(f, "Keywords: {}", list(&self.keywords))?;
+        writeln!(f, "Traits: {}", list(&self.traits))?;
+
+        writeln!(f, "\nOpening hand odds (opening hand / with mulligan)")?;
+        for odds in &self.odds {
+            writeln!(f, "  {:<10} x{}  {:>5.1}%  {:>5.1}%  {}",
+                odds.card_number, odds.copies, odds.opening_hand * 100.0, odds.with_mulligan * 100.0, odds.card_name)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{deck::DeckEntry, test_card};
+    use serde_json::json;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn hypergeometric_odds() {
+        // 1 - C(deck - copies, drawn) / C(deck, drawn)
+        assert_close(chance_to_draw(50, 4, 5), 0.3530395136778115);
+        assert_close(chance_to_draw(50, 1, 5), 0.1);
+        assert_close(chance_to_draw(50, 8, 5), 0.5985066737148144);
+        assert_close(chance_to_draw(50, 4, 6), 0.41054711246200604);
+        assert_close(chance_to_draw(40, 3, 7), 0.44777327935222677);
+    }
+
+    #[test]
+    fn odds_edge_cases() {
+        assert_eq!(chance_to_draw(50, 0, 5), 0.0);
+        assert_eq!(chance_to_draw(50, 4, 0), 0.0);
+        assert_eq!(chance_to_draw(50, 46, 5), 1.0);
+        assert_eq!(chance_to_draw(5, 1, 5), 1.0);
+    }
+
+    #[test]
+    fn mulligan_is_a_second_independent_try() {
+        assert_close(chance_with_mulligan(chance_to_draw(50, 4, 5)), 0.5814421291377574);
+        assert_eq!(chance_with_mulligan(0.0), 0.0);
+        assert_eq!(chance_with_mulligan(1.0), 1.0);
+    }
+
+    #[test]
+    fn stats_count_copies() {
+        let cards = vec![
+            test_card(json!({ "card_number": "OP01-001", "card_type": "LEADER", "cost": "-" })),
+            test_card(json!({ "card_number": "OP01-016", "cost": "2", "counter_value": 1000, "card_effects": ["[Blocker]"] })),
+            test_card(json!({ "card_number": "OP01-025", "cost": "5", "power": "6000", "counter": "-" })),
+            test_card(json!({ "card_number": "OP01-029", "card_type": "EVENT", "cost": "1", "is_counter_event": true, "has_trigger": true })),
+        ];
+        let deck = Deck {
+            leader: "OP01-001".to_string(),
+            cards: [("OP01-016", 4), ("OP01-025", 2), ("OP01-029", 3)]
+                .into_iter()
+                .map(|(card_number, count)| DeckEntry { card_number: card_number.to_string(), count })
+                .collect(),
+        };
+
+        let stats = deck.stats(&cards).unwrap();
+        assert_eq!(stats.card_count, 9);
+        assert_eq!(stats.cost_curve, BTreeMap::from([(1, 3), (2, 4), (5, 2)]));
+        assert_close(stats.average_cost.unwrap(), (3.0 + 8.0 + 10.0) / 9.0);
+        assert_eq!(stats.counters.by_value, BTreeMap::from([(1000, 4)]));
+        assert_eq!((stats.counters.total, stats.counters.no_counter, stats.counters.counter_events), (4000, 2, 3));
+        assert_eq!(stats.trigger_count, 3);
+        assert_eq!(stats.keywords, BTreeMap::from([("Blocker".to_string(), 4)]));
+
+        // A 9 card list still draws from a 50 card deck
+        let odds = stats.odds.iter().find(|odds| odds.card_number == "OP01-016").unwrap();
+        assert_close(odds.opening_hand, chance_to_draw(DECK_SIZE, 4, OPENING_HAND));
+    }
+}
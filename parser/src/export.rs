@@ -0,0 +1,398 @@
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::{fs, io::Write, path::Path};
+
+mod web;
+
+use crate::{is_sentinel, label, parse_number, set_code, unique_cards, Card};
+
+/// Something that can write the card list out in another format
+pub trait Exporter {
+    fn name(&self) -> &'static str;
+    fn export(&self, cards: &[Card], output_dir: &Path) -> Result<(), Box<dyn std::error::Error>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+    Sqlite,
+    /// Minified bundle plus per-set and per-card shards with a hashed manifest, in web/
+    Web,
+}
+
+impl ExportFormat {
+    pub fn exporter(&self) -> Box<dyn Exporter> {
+        match self {
+            ExportFormat::Csv => Box::new(CsvExporter),
+            ExportFormat::Ndjson => Box::new(NdjsonExporter),
+            ExportFormat::Sqlite => Box::new(SqliteExporter),
+            ExportFormat::Web => Box::new(web::WebExporter),
+        }
+    }
+}
+
+/// Runs every requested exporter, writing into `output_dir`
+pub fn run(formats: &[ExportFormat], cards: &[Card], output_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(output_dir)?;
+
+    for format in formats {
+        let exporter = format.exporter();
+        println!("Exporting {} to {}...", exporter.name(), output_dir);
+        exporter.export(cards, Path::new(output_dir))?;
+    }
+
+    Ok(())
+}
+
+// Lists get flattened into a single column, joined with this
+const LIST_SEPARATOR: &str = ";";
+
+#[derive(Debug, Serialize)]
+struct CsvRow {
+    card_number: String,
+    card_name: String,
+    name_key: String,
+    card_type: String,
+    rarity: String,
+    is_alternate_art: bool,
+    colors: String,
+    life: String,
+    cost: String,
+    power: String,
+    counter: String,
+    attributes: String,
+    types: String,
+    block_icon: String,
+    effective_block_icon: String,
+    card_effects: String,
+    effects: String,
+    trigger: String,
+    has_trigger: bool,
+    legality: String,
+    legal_formats: String,
+    counter_value: Option<u32>,
+    is_counter_event: bool,
+    counter_power_boost: Option<u32>,
+    referenced_types: String,
+    referenced_names: String,
+    referenced_card_numbers: String,
+    referenced_attributes: String,
+    card_sets: String,
+    image_name: String,
+    image_url: String,
+    official_image_url: String,
+    image_thumb: String,
+    image_medium: String,
+    image_full: String,
+}
+
+impl From<&Card> for CsvRow {
+    fn from(card: &Card) -> Self {
+        CsvRow {
+            card_number: card.card_number.clone(),
+            card_name: card.card_name.clone(),
+            name_key: card.name_key.clone(),
+            card_type: label(&card.card_type),
+            rarity: label(&card.rarity),
+            is_alternate_art: card.is_alternate_art,
+            colors: card.colors.iter().map(label).collect::<Vec<_>>().join(LIST_SEPARATOR),
+            life: card.life.clone(),
+            cost: card.cost.clone(),
+            power: card.power.clone(),
+            counter: card.counter.clone(),
+            attributes: card.attributes.join(LIST_SEPARATOR),
+            types: card.types.join(LIST_SEPARATOR),
+            block_icon: card.block_icon.clone(),
+            effective_block_icon: card.effective_block_icon.clone(),
+            card_effects: card.card_effects.join(LIST_SEPARATOR),
+            effects: card.effects.clone().unwrap_or_default(),
+            trigger: card.trigger.clone().unwrap_or_default(),
+            has_trigger: card.has_trigger,
+            legality: label(&card.legality.status),
+            legal_formats: card.legal_formats.join(LIST_SEPARATOR),
+            counter_value: card.counter_value,
+            is_counter_event: card.is_counter_event,
+            counter_power_boost: card.counter_power_boost,
+            referenced_types: card.referenced_types.join(LIST_SEPARATOR),
+            referenced_names: card.referenced_names.join(LIST_SEPARATOR),
+            referenced_card_numbers: card.referenced_card_numbers.join(LIST_SEPARATOR),
+            referenced_attributes: card.referenced_attributes.join(LIST_SEPARATOR),
+            card_sets: card.card_sets.clone(),
+            image_name: card.image_name.clone(),
+            image_url: card.image_url.clone(),
+            official_image_url: card.official_image_url.clone(),
+            image_thumb: card.images.as_ref().map(|images| images.thumb.clone()).unwrap_or_default(),
+            image_medium: card.images.as_ref().map(|images| images.medium.clone()).unwrap_or_default(),
+            image_full: card.images.as_ref().map(|images| images.full.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+struct CsvExporter;
+
+impl Exporter for CsvExporter {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn export(&self, cards: &[Card], output_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = csv::Writer::from_path(output_dir.join("cards.csv"))?;
+        for card in cards {
+            writer.serialize(CsvRow::from(card))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+struct NdjsonExporter;
+
+impl Exporter for NdjsonExporter {
+    fn name(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn export(&self, cards: &[Card], output_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = std::io::BufWriter::new(fs::File::create(output_dir.join("cards.ndjson"))?);
+        for card in cards {
+            serde_json::to_writer(&mut file, card)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
+
+struct SqliteExporter;
+
+const SQLITE_SCHEMA: &str = "
+CREATE TABLE cards (
+    card_number TEXT PRIMARY KEY,
+    card_name TEXT NOT NULL,
+    name_key TEXT NOT NULL,
+    card_type TEXT NOT NULL,
+    life INTEGER,
+    cost INTEGER,
+    power INTEGER,
+    counter INTEGER,
+    block_icon TEXT,
+    effects TEXT,
+    trigger TEXT,
+    has_trigger INTEGER NOT NULL,
+    is_counter_event INTEGER NOT NULL,
+    counter_power_boost INTEGER
+);
+
+CREATE TABLE sets (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    code TEXT
+);
+
+CREATE TABLE printings (
+    id INTEGER PRIMARY KEY,
+    card_number TEXT NOT NULL REFERENCES cards(card_number),
+    set_id INTEGER REFERENCES sets(id),
+    image_name TEXT NOT NULL,
+    image_url TEXT NOT NULL,
+    official_image_url TEXT NOT NULL,
+    rarity TEXT NOT NULL,
+    is_alternate_art INTEGER NOT NULL
+);
+
+CREATE TABLE colors (
+    card_number TEXT NOT NULL REFERENCES cards(card_number),
+    color TEXT NOT NULL,
+    PRIMARY KEY (card_number, color)
+);
+
+CREATE TABLE types (
+    card_number TEXT NOT NULL REFERENCES cards(card_number),
+    type TEXT NOT NULL,
+    PRIMARY KEY (card_number, type)
+);
+
+CREATE TABLE attributes (
+    card_number TEXT NOT NULL REFERENCES cards(card_number),
+    attribute TEXT NOT NULL,
+    PRIMARY KEY (card_number, attribute)
+);
+
+CREATE TABLE effects (
+    card_number TEXT NOT NULL REFERENCES cards(card_number),
+    effect TEXT NOT NULL,
+    PRIMARY KEY (card_number, effect)
+);
+
+CREATE TABLE card_references (
+    card_number TEXT NOT NULL REFERENCES cards(card_number),
+    referenced_card_number TEXT NOT NULL REFERENCES cards(card_number),
+    PRIMARY KEY (card_number, referenced_card_number)
+);
+
+CREATE INDEX cards_name_key ON cards(name_key);
+CREATE INDEX printings_card_number ON printings(card_number);
+CREATE INDEX types_type ON types(type);
+CREATE INDEX effects_effect ON effects(effect);
+
+CREATE VIRTUAL TABLE cards_fts USING fts5(card_number UNINDEXED, card_name, effects, trigger);
+";
+
+impl Exporter for SqliteExporter {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn export(&self, cards: &[Card], output_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let path = output_dir.join("cards.sqlite");
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+
+        let mut connection = Connection::open(&path)?;
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(SQLITE_SCHEMA)?;
+
+        for card in unique_cards(cards) {
+            transaction.execute(
+                "INSERT INTO cards VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![
+                    card.card_number,
+                    card.card_name,
+                    card.name_key,
+                    label(&card.card_type),
+                    parse_number(&card.life),
+                    parse_number(&card.cost),
+                    parse_number(&card.power),
+                    parse_number(&card.counter),
+                    card.block_icon,
+                    card.effects,
+                    card.trigger,
+                    card.has_trigger,
+                    card.is_counter_event,
+                    card.counter_power_boost,
+                ],
+            )?;
+
+            for color in &card.colors {
+                transaction.execute("INSERT OR IGNORE INTO colors VALUES (?1, ?2)", params![card.card_number, label(color)])?;
+            }
+            for card_type in card.types.iter().filter(|value| !is_sentinel(value)) {
+                transaction.execute("INSERT OR IGNORE INTO types VALUES (?1, ?2)", params![card.card_number, card_type])?;
+            }
+            for attribute in card.attributes.iter().filter(|value| !is_sentinel(value)) {
+                transaction.execute("INSERT OR IGNORE INTO attributes VALUES (?1, ?2)", params![card.card_number, attribute])?;
+            }
+            for effect in card.card_effects.iter().filter(|value| !is_sentinel(value)) {
+                transaction.execute("INSERT OR IGNORE INTO effects VALUES (?1, ?2)", params![card.card_number, effect])?;
+            }
+            for referenced in &card.referenced_card_numbers {
+                transaction.execute("INSERT OR IGNORE INTO card_references VALUES (?1, ?2)", params![card.card_number, referenced])?;
+            }
+
+            transaction.execute(
+                "INSERT INTO cards_fts VALUES (?1, ?2, ?3, ?4)",
+                params![card.card_number, card.card_name, card.effects, card.trigger],
+            )?;
+        }
+
+        for card in cards {
+            let set_id = if is_sentinel(&card.card_sets) {
+                None
+            } else {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO sets (name, code) VALUES (?1, ?2)",
+                    params![card.card_sets, set_code(&card.card_sets)],
+                )?;
+                Some(transaction.query_row(
+                    "SELECT id FROM sets WHERE name = ?1",
+                    params![card.card_sets],
+                    |row| row.get::<_, i64>(0),
+                )?)
+            };
+
+            transaction.execute(
+                "INSERT INTO printings (card_number, set_id, image_name, image_url, official_image_url, rarity, is_alternate_art) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    card.card_number,
+                    set_id,
+                    card.image_name,
+                    card.image_url,
+                    card.official_image_url,
+                    label(&card.rarity),
+                    card.is_alternate_art,
+                ],
+            )?;
+        }
+
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_card;
+    use serde_json::json;
+
+    fn output_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("export-{}-test-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cards() -> Vec<Card> {
+        vec![
+            test_card(json!({
+                "card_number": "OP01-013",
+                "card_name": "Sanji",
+                "effects": "[DON!! x1] [When Attacking] K.O. up to 1 of your opponent's Characters with a cost of 2 or less, then say \"Diable Jambe\".\nThis card gains +1000 power.",
+            })),
+            test_card(json!({
+                "card_number": "OP01-016",
+                "card_name": "Nami",
+                "effects": "[On Play] Look at 5 cards from the top of your deck.",
+            })),
+        ]
+    }
+
+    #[test]
+    fn csv_quotes_effect_text() {
+        let dir = output_dir("csv");
+        CsvExporter.export(&cards(), &dir).unwrap();
+
+        let content = fs::read_to_string(dir.join("cards.csv")).unwrap();
+        assert!(content.contains("\"[DON!! x1] [When Attacking] K.O. up to 1 of your opponent's Characters with a cost of 2 or less, then say \"\"Diable Jambe\"\".\nThis card gains +1000 power.\""));
+
+        let mut reader = csv::Reader::from_path(dir.join("cards.csv")).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let effects = headers.iter().position(|header| header == "effects").unwrap();
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].len(), headers.len());
+        assert_eq!(&rows[0][effects], cards()[0].effects.as_deref().unwrap());
+    }
+
+    #[test]
+    fn sqlite_full_text_search_finds_effects() {
+        let dir = output_dir("sqlite");
+        SqliteExporter.export(&cards(), &dir).unwrap();
+
+        let connection = Connection::open(dir.join("cards.sqlite")).unwrap();
+        let matches: Vec<String> = connection
+            .prepare("SELECT card_number FROM cards_fts WHERE cards_fts MATCH ?1")
+            .unwrap()
+            .query_map(params!["jambe"], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(matches, ["OP01-013"]);
+
+        let printings: i64 = connection.query_row("SELECT COUNT(*) FROM printings", [], |row| row.get(0)).unwrap();
+        assert_eq!(printings, 2);
+    }
+}
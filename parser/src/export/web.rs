@@ -0,0 +1,142 @@
+use serde::Serialize;
+use std::{fs, collections::BTreeMap, path::Path};
+
+use crate::{set_key, sha256_hex, Card};
+use super::Exporter;
+
+/// Minified bundle plus per-set (`web/sets/OP-01.json`) and per-card
+/// (`web/cards/OP01-001.json`) shards so a static host can serve small payloads
+pub struct WebExporter;
+
+#[derive(Debug, Serialize)]
+struct Manifest {
+    card_count: usize,
+    bundle: ManifestEntry,
+    sets: BTreeMap<String, ManifestEntry>,
+    cards: BTreeMap<String, ManifestEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    path: String,
+    sha256: String,
+    bytes: usize,
+    cards: usize,
+}
+
+impl Exporter for WebExporter {
+    fn name(&self) -> &'static str {
+        "web"
+    }
+
+    fn export(&self, cards: &[Card], output_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        // Everything lives in its own directory that gets rewritten from scratch,
+        // so removed cards don't linger and nothing else in output_dir is touched
+        let output_dir = &output_dir.join("web");
+        if output_dir.exists() {
+            fs::remove_dir_all(output_dir)?;
+        }
+        for shard_dir in ["sets", "cards"] {
+            fs::create_dir_all(output_dir.join(shard_dir))?;
+        }
+
+        let mut by_set: BTreeMap<String, Vec<&Card>> = BTreeMap::new();
+        let mut by_number: BTreeMap<&str, Vec<&Card>> = BTreeMap::new();
+
+        for card in cards {
+            if let Some(key) = set_key(&card.card_sets) {
+                by_set.entry(key).or_default().push(card);
+            }
+            by_number.entry(&card.card_number).or_default().push(card);
+        }
+
+        let bundle = write_minified(output_dir, "cards.min.json", cards)?;
+
+        let mut sets = BTreeMap::new();
+        for (key, set_cards) in by_set {
+            let entry = write_minified(output_dir, &format!("sets/{}.json", key), &set_cards)?;
+            sets.insert(key, entry);
+        }
+
+        let mut card_entries = BTreeMap::new();
+        for (card_number, printings) in by_number {
+            let entry = write_minified(output_dir, &format!("cards/{}.json", card_number), &printings)?;
+            card_entries.insert(card_number.to_string(), entry);
+        }
+
+        let manifest = Manifest {
+            card_count: cards.len(),
+            bundle,
+            sets,
+            cards: card_entries,
+        };
+
+        fs::write(output_dir.join("index.json"), serde_json::to_string_pretty(&manifest)?)?;
+
+        Ok(())
+    }
+}
+
+fn write_minified<T: Serialize>(output_dir: &Path, path: &str, cards: &[T]) -> Result<ManifestEntry, Box<dyn std::error::Error>> {
+    let content = serde_json::to_string(cards)?;
+    fs::write(output_dir.join(path), &content)?;
+
+    Ok(ManifestEntry {
+        path: path.to_string(),
+        sha256: sha256_hex(content.as_bytes()),
+        bytes: content.len(),
+        cards: cards.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_card;
+    use serde_json::{json, Value};
+
+    fn read_json(path: &Path) -> Value {
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn writes_shards_with_hashed_manifest() {
+        let dir = std::env::temp_dir().join(format!("web-export-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("web/cards")).unwrap();
+        fs::write(dir.join("cards.csv"), "kept").unwrap();
+        fs::write(dir.join("web/cards/ST01-001.json"), "[]").unwrap();
+
+        let cards = vec![
+            test_card(json!({ "card_number": "OP01-001", "card_name": "Roronoa Zoro" })),
+            test_card(json!({ "card_number": "OP01-001", "card_name": "Roronoa Zoro", "is_alternate_art": true, "image_name": "OP01-001_p1" })),
+            test_card(json!({ "card_number": "OP02-001", "card_name": "Edward.Newgate", "card_sets": "-PARAMOUNT WAR- [OP02]" })),
+        ];
+        WebExporter.export(&cards, &dir).unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("cards.csv")).unwrap(), "kept");
+        assert!(!dir.join("web/cards/ST01-001.json").exists());
+
+        let web = dir.join("web");
+        let manifest = read_json(&web.join("index.json"));
+        assert_eq!(manifest["card_count"], 3);
+        assert_eq!(manifest["bundle"]["cards"], 3);
+
+        let sets = manifest["sets"].as_object().unwrap();
+        assert_eq!(sets.keys().collect::<Vec<_>>(), ["paramount-war-op02", "romance-dawn-op01"]);
+        assert_eq!(sets["romance-dawn-op01"]["cards"], 2);
+
+        let zoro = read_json(&web.join("cards/OP01-001.json"));
+        assert_eq!(zoro.as_array().unwrap().len(), 2);
+        assert_eq!(zoro[1]["image_name"], "OP01-001_p1");
+
+        let entries = std::iter::once(&manifest["bundle"])
+            .chain(sets.values())
+            .chain(manifest["cards"].as_object().unwrap().values());
+        for entry in entries {
+            let content = fs::read(web.join(entry["path"].as_str().unwrap())).unwrap();
+            assert_eq!(entry["sha256"], sha256_hex(&content));
+            assert_eq!(entry["bytes"], content.len());
+        }
+    }
+}
@@ -0,0 +1,432 @@
+use reqwest::{header, Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path, sync::Arc, time::Duration};
+use tokio::{sync::Semaphore, task::JoinSet};
+
+mod art;
+mod derivatives;
+mod urls;
+
+pub use art::{art_report, SAME_ART_DISTANCE};
+pub use derivatives::{generate_derivatives, CardImages, DerivativeFormat};
+pub use urls::{ImageUrlConfig, ImageUrls};
+
+use crate::{sha256_hex, Card};
+
+/// Bump when the layout of the manifest changes
+pub const MANIFEST_VERSION: u32 = 1;
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+// Downloads between manifest saves, so an interrupted run only loses a few
+const SAVE_EVERY: usize = 50;
+
+/// What's in a local image mirror, written to `{mirror}/manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    /// Keyed by image_name
+    pub images: BTreeMap<String, ImageEntry>,
+    /// Images that couldn't be mirrored on the last run
+    pub failures: Vec<ImageFailure>,
+    /// Mirrored images that came back different on the last run
+    #[serde(default)]
+    pub changes: Vec<ImageChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageEntry {
+    pub url: String,
+    /// Relative to the mirror directory
+    pub file: String,
+    pub bytes: u64,
+    pub width: u32,
+    pub height: u32,
+    pub sha256: String,
+    /// Validators sent back on the next run, so unchanged images aren't downloaded again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// See `art::perceptual_hash`, None for images mirrored before hashing
+    #[serde(default)]
+    pub perceptual_hash: Option<String>,
+}
+
+/// An image that was downloaded again with different content
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageChange {
+    pub image_name: String,
+    pub previous_sha256: String,
+    pub sha256: String,
+    /// Bits between the old and new perceptual hash, when both are known
+    pub distance: Option<u32>,
+    /// The distance is over `SAME_ART_DISTANCE`, so it's more than a re-encode
+    pub art_changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageFailure {
+    pub image_name: String,
+    pub url: String,
+    pub kind: FailureKind,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    /// The card has no image URL, or the server doesn't have the image
+    Missing,
+    /// Any other error status, a network error, or a body that isn't an image
+    Broken,
+}
+
+#[derive(Debug)]
+pub struct MirrorOptions {
+    /// Fetch from here instead of the official site, keeping each image's file name
+    pub base_url: Option<String>,
+    pub concurrency: usize,
+    /// Skip images that are already mirrored instead of checking them for changes
+    pub missing_only: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct MirrorSummary {
+    pub downloaded: usize,
+    pub unchanged: usize,
+    pub skipped: usize,
+    pub failures: Vec<ImageFailure>,
+    pub changes: Vec<ImageChange>,
+}
+
+enum Outcome {
+    Downloaded(ImageEntry, Option<ImageChange>),
+    Unchanged(ImageEntry),
+    Failed(ImageFailure),
+}
+
+impl Manifest {
+    /// The manifest in `dir`, or an empty one for a new mirror
+    pub fn load(dir: &str) -> Result<Manifest, Box<dyn std::error::Error>> {
+        let path = format!("{}/manifest.json", dir);
+        if !Path::new(&path).exists() {
+            return Ok(Manifest { version: MANIFEST_VERSION, images: BTreeMap::new(), failures: Vec::new(), changes: Vec::new() });
+        }
+
+        let manifest: Manifest = serde_json::from_str(&fs::read_to_string(&path)?).map_err(|err| format!("{}: {}", path, err))?;
+        if manifest.version != MANIFEST_VERSION {
+            return Err(format!("{}: unsupported manifest version {}, expected {}", path, manifest.version, MANIFEST_VERSION).into());
+        }
+        Ok(manifest)
+    }
+
+    pub fn save(&self, dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+        write_atomic(&format!("{}/manifest.json", dir), serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    // An entry whose file is still on disk, anything else gets downloaded from scratch
+    fn mirrored(&self, dir: &str, image_name: &str) -> Option<&ImageEntry> {
+        self.images.get(image_name).filter(|entry| {
+            fs::metadata(format!("{}/{}", dir, entry.file)).is_ok_and(|metadata| metadata.len() == entry.bytes)
+        })
+    }
+}
+
+/// Downloads every unique `image_name` in `cards` into `dir` and updates its
+/// manifest. Mirrored images are only downloaded again when the server says
+/// they changed, and the manifest is saved as it goes so a rerun picks up
+/// where an interrupted one stopped.
+pub async fn mirror(cards: &[Card], dir: &str, options: &MirrorOptions) -> Result<MirrorSummary, Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+
+    let mut manifest = Manifest::load(dir)?;
+    manifest.failures.clear();
+    manifest.changes.clear();
+
+    // image_url can point at our own CDN, the official site is the source
+    let mut images: BTreeMap<&str, &str> = BTreeMap::new();
+    for card in cards {
+        let official_url = if card.official_image_url.is_empty() { &card.image_url } else { &card.official_image_url };
+        images.entry(&card.image_name).or_insert(official_url);
+    }
+
+    let client = Client::builder()
+        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:122.0) Gecko/20100101 Firefox/122.0")
+        .timeout(Duration::from_secs(60))
+        .build()?;
+    let permits = Arc::new(Semaphore::new(options.concurrency.max(1)));
+    let mut summary = MirrorSummary::default();
+    let mut tasks = JoinSet::new();
+
+    for (image_name, image_url) in images {
+        if image_url.is_empty() {
+            summary.failures.push(ImageFailure {
+                image_name: image_name.to_string(),
+                url: String::new(),
+                kind: FailureKind::Missing,
+                error: "No image URL".to_string(),
+            });
+            continue;
+        }
+
+        let existing = manifest.mirrored(dir, image_name).cloned();
+        if options.missing_only && existing.is_some() {
+            summary.skipped += 1;
+            continue;
+        }
+
+        let url = source_url(image_url, options.base_url.as_deref());
+        let file = format!("{}.{}", image_name, extension(image_url));
+        let (client, permits, dir, image_name) = (client.clone(), permits.clone(), dir.to_string(), image_name.to_string());
+
+        tasks.spawn(async move {
+            let _permit = permits.acquire_owned().await.expect("semaphore is never closed");
+            fetch(&client, &image_name, &url, &dir, &file, existing).await
+        });
+    }
+
+    let total = tasks.len();
+    let mut done = 0;
+
+    while let Some(result) = tasks.join_next().await {
+        match result? {
+            (image_name, Outcome::Downloaded(entry, change)) => {
+                summary.downloaded += 1;
+                summary.changes.extend(change);
+                manifest.images.insert(image_name, entry);
+            }
+            (image_name, Outcome::Unchanged(entry)) => {
+                summary.unchanged += 1;
+                manifest.images.insert(image_name, entry);
+            }
+            (_, Outcome::Failed(failure)) => summary.failures.push(failure),
+        }
+
+        done += 1;
+        if done % SAVE_EVERY == 0 {
+            manifest.save(dir)?;
+            println!("  {}/{} images checked", done, total);
+        }
+    }
+
+    summary.failures.sort_by(|a, b| a.image_name.cmp(&b.image_name));
+    summary.changes.sort_by(|a, b| a.image_name.cmp(&b.image_name));
+    manifest.failures = summary.failures.clone();
+    manifest.changes = summary.changes.clone();
+    manifest.save(dir)?;
+
+    Ok(summary)
+}
+
+async fn fetch(client: &Client, image_name: &str, url: &str, dir: &str, file: &str, existing: Option<ImageEntry>) -> (String, Outcome) {
+    let failed = |kind, error: String| {
+        (image_name.to_string(), Outcome::Failed(ImageFailure {
+            image_name: image_name.to_string(),
+            url: url.to_string(),
+            kind,
+            error,
+        }))
+    };
+
+    let mut request = client.get(url);
+    if let Some(entry) = existing.as_ref().filter(|entry| entry.url == url) {
+        if let Some(etag) = &entry.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &entry.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(err) => return failed(FailureKind::Broken, err.to_string()),
+    };
+
+    let status = response.status();
+    if let (StatusCode::NOT_MODIFIED, Some(entry)) = (status, &existing) {
+        let mut entry = entry.clone();
+        // Hash images mirrored before there were perceptual hashes from the local copy
+        if entry.perceptual_hash.is_none() {
+            let path = format!("{}/{}", dir, entry.file);
+            entry.perceptual_hash = tokio::task::spawn_blocking(move || image::open(path).ok().map(|image| art::perceptual_hash(&image)))
+                .await
+                .ok()
+                .flatten();
+        }
+        return (image_name.to_string(), Outcome::Unchanged(entry));
+    }
+    if matches!(status, StatusCode::NOT_FOUND | StatusCode::GONE) {
+        return failed(FailureKind::Missing, format!("HTTP {}", status));
+    }
+    if !status.is_success() {
+        return failed(FailureKind::Broken, format!("HTTP {}", status));
+    }
+
+    let validator = |name| response.headers().get(name).and_then(|value| value.to_str().ok()).map(String::from);
+    let etag = validator(header::ETAG);
+    let last_modified = validator(header::LAST_MODIFIED);
+
+    let content = match response.bytes().await {
+        Ok(content) => content,
+        Err(err) => return failed(FailureKind::Broken, err.to_string()),
+    };
+
+    // Decoding and hashing are CPU bound, keep them off the download tasks
+    let decoding = content.clone();
+    let decoded = tokio::task::spawn_blocking(move || {
+        image::load_from_memory(&decoding).map(|decoded| (decoded.width(), decoded.height(), art::perceptual_hash(&decoded)))
+    }).await;
+
+    let (width, height, perceptual_hash) = match decoded {
+        Ok(Ok(decoded)) => decoded,
+        Ok(Err(err)) => return failed(FailureKind::Broken, format!("Not an image: {}", err)),
+        Err(err) => return failed(FailureKind::Broken, err.to_string()),
+    };
+
+    let entry = ImageEntry {
+        url: url.to_string(),
+        file: file.to_string(),
+        bytes: content.len() as u64,
+        width,
+        height,
+        sha256: sha256_hex(&content),
+        etag,
+        last_modified,
+        perceptual_hash: Some(perceptual_hash),
+    };
+
+    // Servers that ignore the validators still send the same bytes back
+    if existing.as_ref().is_some_and(|existing| existing.sha256 == entry.sha256 && existing.file == entry.file) {
+        return (image_name.to_string(), Outcome::Unchanged(entry));
+    }
+
+    let change = existing.map(|existing| {
+        let distance = existing.perceptual_hash.as_deref()
+            .zip(entry.perceptual_hash.as_deref())
+            .and_then(|(previous, current)| art::hash_distance(previous, current));
+
+        ImageChange {
+            image_name: image_name.to_string(),
+            previous_sha256: existing.sha256,
+            sha256: entry.sha256.clone(),
+            distance,
+            art_changed: distance.is_none_or(|distance| distance > SAME_ART_DISTANCE),
+        }
+    });
+
+    if let Err(err) = write_atomic(&format!("{}/{}", dir, file), &content) {
+        return failed(FailureKind::Broken, format!("Couldn't write {}: {}", file, err));
+    }
+    (image_name.to_string(), Outcome::Downloaded(entry, change))
+}
+
+/// Where to fetch an image from, `base_url` swaps out everything but the file name
+pub fn source_url(image_url: &str, base_url: Option<&str>) -> String {
+    match base_url {
+        Some(base_url) => format!("{}/{}", base_url.trim_end_matches('/'), file_name(image_url)),
+        None => image_url.to_string(),
+    }
+}
+
+// Last path segment of a URL, without any query string or fragment
+fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    path.rsplit('/').next().unwrap_or_default()
+}
+
+fn extension(url: &str) -> &str {
+    file_name(url)
+        .rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .filter(|extension| !extension.is_empty())
+        .unwrap_or("png")
+}
+
+// Written next to the target and renamed, so an interrupted run never leaves half a file
+fn write_atomic(path: &str, content: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let partial = format!("{}.part", path);
+    fs::write(&partial, content)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_card;
+    use axum::{http::{HeaderMap, StatusCode as HttpStatus}, response::IntoResponse, routing::get, Router};
+    use serde_json::json;
+    use std::{io::Cursor, sync::atomic::{AtomicUsize, Ordering}};
+
+    const ETAG: &str = "\"v1\"";
+    const LAST_MODIFIED: &str = "Wed, 01 Jan 2025 00:00:00 GMT";
+
+    fn png() -> Vec<u8> {
+        let image = image::RgbImage::from_fn(32, 24, |x, y| image::Rgb([(x * 8) as u8, (y * 10) as u8, 128]));
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    // Serves ok.png with validators, broken.png that isn't an image and 404 for the rest
+    async fn start_server(revalidations: Arc<AtomicUsize>) -> String {
+        let image = png();
+        let app = Router::new()
+            .route("/ok.png", get(move |headers: HeaderMap| async move {
+                if headers.get("if-none-match").is_some_and(|value| value == ETAG) {
+                    revalidations.fetch_add(1, Ordering::SeqCst);
+                    return HttpStatus::NOT_MODIFIED.into_response();
+                }
+                ([("etag", ETAG), ("last-modified", LAST_MODIFIED), ("content-type", "image/png")], image).into_response()
+            }))
+            .route("/broken.png", get(|| async { "<html>Not found</html>" }));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", address)
+    }
+
+    fn card(image_name: &str, file: &str) -> Card {
+        test_card(json!({
+            "image_name": image_name,
+            "official_image_url": format!("https://en.onepiece-cardgame.com/images/cardlist/card/{}", file),
+        }))
+    }
+
+    #[tokio::test]
+    async fn mirror_downloads_revalidates_and_records_failures() {
+        let dir = std::env::temp_dir().join(format!("images-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let dir = dir.to_str().unwrap();
+
+        let revalidations = Arc::new(AtomicUsize::new(0));
+        let options = MirrorOptions { base_url: Some(start_server(revalidations.clone()).await), concurrency: 2, missing_only: false };
+        let cards = vec![card("OK", "ok.png"), card("OK", "ok.png"), card("MISSING", "missing.png"), card("BROKEN", "broken.png")];
+
+        let summary = mirror(&cards, dir, &options).await.unwrap();
+        assert_eq!((summary.downloaded, summary.unchanged, summary.skipped), (1, 0, 0));
+        assert_eq!(fs::read(format!("{}/OK.png", dir)).unwrap(), png());
+
+        let failures: Vec<(&str, FailureKind)> = summary.failures.iter().map(|failure| (failure.image_name.as_str(), failure.kind)).collect();
+        assert_eq!(failures, vec![("BROKEN", FailureKind::Broken), ("MISSING", FailureKind::Missing)]);
+
+        let manifest = Manifest::load(dir).unwrap();
+        let entry = manifest.images["OK"].clone();
+        assert_eq!((entry.file.as_str(), entry.width, entry.height), ("OK.png", 32, 24));
+        assert_eq!(entry.sha256, sha256_hex(&png()));
+        assert_eq!((entry.etag.as_deref(), entry.last_modified.as_deref()), (Some(ETAG), Some(LAST_MODIFIED)));
+        assert!(entry.perceptual_hash.is_some());
+        assert_eq!(manifest.failures.len(), 2);
+        assert!(!manifest.images.contains_key("MISSING") && !manifest.images.contains_key("BROKEN"));
+
+        // The second run sends the validators and keeps the entry on a 304
+        let summary = mirror(&cards, dir, &options).await.unwrap();
+        assert_eq!((summary.downloaded, summary.unchanged), (0, 1));
+        assert_eq!(revalidations.load(Ordering::SeqCst), 1);
+        assert_eq!(Manifest::load(dir).unwrap().images["OK"].sha256, entry.sha256);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+use image::{imageops::FilterType, DynamicImage};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::Manifest;
+use crate::Card;
+
+/// Perceptual hashes at most this many bits apart are taken to be the same art.
+/// Stamps, set codes and re-encoding stay well under it, a different art doesn't.
+pub const SAME_ART_DISTANCE: u32 = 8;
+
+/// 64 bit difference hash as hex: the image is shrunk to 9x8 in grayscale and
+/// each bit says whether a pixel is brighter than its right neighbour. It
+/// survives resizing, compression and small edits, unlike the content hash.
+pub fn perceptual_hash(image: &DynamicImage) -> String {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash: u64 = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = (hash << 1) | u64::from(small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0]);
+        }
+    }
+
+    format!("{:016x}", hash)
+}
+
+/// Number of differing bits between two hashes, None if either isn't one
+pub fn hash_distance(a: &str, b: &str) -> Option<u32> {
+    let a = u64::from_str_radix(a, 16).ok()?;
+    let b = u64::from_str_radix(b, 16).ok()?;
+    Some((a ^ b).count_ones())
+}
+
+/// Written to `{mirror}/art-report.json`
+#[derive(Debug, Serialize)]
+pub struct ArtReport {
+    pub max_distance: u32,
+    /// Printings without a hashed image in the mirror, left out of everything below
+    pub unhashed: Vec<String>,
+    /// Printings that share an art, only groups of two or more. Printings with
+    /// the same image, like a reprint in another set, are always together.
+    pub groups: Vec<ArtGroup>,
+    pub suspect_alternate_arts: Vec<SuspectArt>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArtGroup {
+    /// Hash of the first image in the group
+    pub perceptual_hash: String,
+    pub card_numbers: Vec<String>,
+    pub printings: Vec<ArtPrinting>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArtPrinting {
+    pub image_name: String,
+    pub card_number: String,
+    pub card_sets: String,
+    pub is_alternate_art: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SuspectArt {
+    pub image_name: String,
+    pub card_number: String,
+    pub is_alternate_art: bool,
+    pub reason: SuspectReason,
+    /// The card number's regular image it was compared with
+    pub regular_image_name: String,
+    pub distance: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuspectReason {
+    /// Marked alternate art, but it's the regular art, e.g. a plain reprint
+    SameArtAsRegular,
+    /// Not marked alternate art, but the art isn't the regular one
+    DifferentArtFromRegular,
+}
+
+/// Groups the mirrored images of `cards` by art and compares every printing
+/// with the regular image of its card number, the one named after it
+pub fn art_report(cards: &[Card], manifest: &Manifest, max_distance: u32) -> ArtReport {
+    let hash = |image_name: &str| {
+        manifest.images.get(image_name)
+            .and_then(|entry| entry.perceptual_hash.as_deref())
+            .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+    };
+
+    // Reprints share an image, so every image can have several printings
+    let mut printings: BTreeMap<&str, Vec<&Card>> = BTreeMap::new();
+    for card in cards {
+        printings.entry(&card.image_name).or_default().push(card);
+    }
+
+    let (hashed, unhashed): (Vec<_>, Vec<_>) = printings.into_iter().partition(|(image_name, _)| hash(image_name).is_some());
+    let hashes: Vec<u64> = hashed.iter().map(|(image_name, _)| hash(image_name).unwrap_or_default()).collect();
+
+    // Union find, so arts that chain together within the distance end up in one group
+    let mut parents: Vec<usize> = (0..hashed.len()).collect();
+
+    for a in 0..hashed.len() {
+        for b in a + 1..hashed.len() {
+            if (hashes[a] ^ hashes[b]).count_ones() <= max_distance {
+                let (root_a, root_b) = (root(&mut parents, a), root(&mut parents, b));
+                parents[root_b] = root_a;
+            }
+        }
+    }
+
+    let mut grouped: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for index in 0..hashed.len() {
+        let group = root(&mut parents, index);
+        grouped.entry(group).or_default().push(index);
+    }
+
+    let groups: Vec<ArtGroup> = grouped.into_values()
+        .map(|members| {
+            let printings: Vec<ArtPrinting> = members.iter()
+                .flat_map(|index| {
+                    let (image_name, cards) = &hashed[*index];
+                    cards.iter().map(move |card| ArtPrinting {
+                        image_name: image_name.to_string(),
+                        card_number: card.card_number.clone(),
+                        card_sets: card.card_sets.clone(),
+                        is_alternate_art: card.is_alternate_art,
+                    })
+                })
+                .collect();
+
+            let mut card_numbers: Vec<String> = printings.iter().map(|printing| printing.card_number.clone()).collect();
+            card_numbers.sort();
+            card_numbers.dedup();
+
+            ArtGroup { perceptual_hash: format!("{:016x}", hashes[members[0]]), card_numbers, printings }
+        })
+        .filter(|group| group.printings.len() > 1)
+        .collect();
+
+    let mut suspect_alternate_arts = Vec::new();
+    let hashed_printings = hashed.iter().zip(&hashes)
+        .flat_map(|((image_name, cards), image_hash)| cards.iter().map(move |card| (*image_name, *card, image_hash)));
+    for (image_name, card, image_hash) in hashed_printings {
+        let Some(regular) = hash(&card.card_number).filter(|_| image_name != card.card_number) else {
+            continue;
+        };
+        let distance = (image_hash ^ regular).count_ones();
+
+        let reason = match (card.is_alternate_art, distance <= max_distance) {
+            (true, true) => SuspectReason::SameArtAsRegular,
+            (false, false) => SuspectReason::DifferentArtFromRegular,
+            _ => continue,
+        };
+
+        suspect_alternate_arts.push(SuspectArt {
+            image_name: image_name.to_string(),
+            card_number: card.card_number.clone(),
+            is_alternate_art: card.is_alternate_art,
+            reason,
+            regular_image_name: card.card_number.clone(),
+            distance,
+        });
+    }
+
+    ArtReport {
+        max_distance,
+        unhashed: unhashed.into_iter().map(|(image_name, _)| image_name.to_string()).collect(),
+        groups,
+        suspect_alternate_arts,
+    }
+}
+
+fn root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::images::ImageEntry;
+    use serde_json::json;
+
+    fn entry(perceptual_hash: &str) -> ImageEntry {
+        ImageEntry {
+            url: String::new(),
+            file: String::new(),
+            bytes: 0,
+            width: 0,
+            height: 0,
+            sha256: String::new(),
+            etag: None,
+            last_modified: None,
+            perceptual_hash: Some(perceptual_hash.to_string()),
+        }
+    }
+
+    #[test]
+    fn reprints_of_one_image_are_all_reported() {
+        let cards = [
+            crate::test_card(json!({})),
+            crate::test_card(json!({ "card_sets": "-STARTER DECK- [ST01]" })),
+            crate::test_card(json!({ "image_name": "OP01-001_p1", "is_alternate_art": true })),
+            crate::test_card(json!({ "image_name": "OP01-001_p1", "is_alternate_art": true, "card_sets": "-PROMO-" })),
+        ];
+        let manifest = Manifest {
+            version: 1,
+            images: BTreeMap::from([
+                ("OP01-001".to_string(), entry("00000000000000ff")),
+                ("OP01-001_p1".to_string(), entry("00000000000000fe")),
+            ]),
+            failures: Vec::new(),
+            changes: Vec::new(),
+        };
+
+        let report = art_report(&cards, &manifest, SAME_ART_DISTANCE);
+
+        assert_eq!(report.groups.len(), 1);
+        let sets: Vec<&str> = report.groups[0].printings.iter().map(|printing| printing.card_sets.as_str()).collect();
+        assert_eq!(sets, ["-ROMANCE DAWN- [OP01]", "-STARTER DECK- [ST01]", "-ROMANCE DAWN- [OP01]", "-PROMO-"]);
+        assert_eq!(report.suspect_alternate_arts.len(), 2);
+        assert!(report.suspect_alternate_arts.iter().all(|suspect| suspect.reason == SuspectReason::SameArtAsRegular));
+    }
+}
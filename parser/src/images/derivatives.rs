@@ -0,0 +1,324 @@
+use clap::ValueEnum;
+use image::{
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    DynamicImage,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path, sync::Mutex, thread};
+
+use super::{write_atomic, ImageEntry, Manifest};
+use crate::label;
+
+/// Bump when the layout of the derivatives manifest changes
+pub const DERIVATIVES_VERSION: u32 = 1;
+
+const JPEG_QUALITY: u8 = 85;
+const AVIF_QUALITY: u8 = 70;
+// 1 is the slowest and smallest, 10 the fastest
+const AVIF_SPEED: u8 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSize {
+    Thumb,
+    Medium,
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum DerivativeFormat {
+    /// Lossless only, the image crate has no lossy WebP encoder
+    Webp,
+    /// Smallest, but slow to encode
+    Avif,
+    /// The format `images` on each card points at by default
+    Jpeg,
+}
+
+impl ImageSize {
+    pub const ALL: [ImageSize; 3] = [ImageSize::Thumb, ImageSize::Medium, ImageSize::Full];
+
+    /// Width to scale down to, None keeps the original size
+    pub fn width(&self) -> Option<u32> {
+        match self {
+            ImageSize::Thumb => Some(200),
+            ImageSize::Medium => Some(480),
+            ImageSize::Full => None,
+        }
+    }
+}
+
+impl DerivativeFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DerivativeFormat::Webp => "webp",
+            DerivativeFormat::Avif => "avif",
+            DerivativeFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Written to `{mirror}/derivatives.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivativeManifest {
+    pub version: u32,
+    /// Keyed by image_name
+    pub images: BTreeMap<String, DerivativeSet>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DerivativeSet {
+    /// Hash of the mirrored image these were made from, a new hash redoes them all
+    pub source_sha256: String,
+    pub derivatives: Vec<Derivative>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Derivative {
+    pub size: ImageSize,
+    pub format: DerivativeFormat,
+    /// Relative to the mirror directory
+    pub file: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: u64,
+}
+
+/// Where each size of a card's image is, from the `images` URL template
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct CardImages {
+    pub thumb: String,
+    pub medium: String,
+    pub full: String,
+}
+
+/// Where a derivative lives, e.g. `thumb/OP01-001.webp`
+pub fn derivative_file(image_name: &str, size: ImageSize, format: DerivativeFormat) -> String {
+    format!("{}/{}.{}", label(&size), image_name, format.extension())
+}
+
+#[derive(Debug, Default)]
+pub struct DerivativeSummary {
+    pub generated: usize,
+    pub up_to_date: usize,
+    /// image_name and error, for mirrored images that couldn't be decoded or encoded
+    pub failures: Vec<(String, String)>,
+}
+
+impl DerivativeManifest {
+    pub fn load(dir: &str) -> Result<DerivativeManifest, Box<dyn std::error::Error>> {
+        let path = format!("{}/derivatives.json", dir);
+        if !Path::new(&path).exists() {
+            return Ok(DerivativeManifest { version: DERIVATIVES_VERSION, images: BTreeMap::new() });
+        }
+
+        let manifest: DerivativeManifest = serde_json::from_str(&fs::read_to_string(&path)?).map_err(|err| format!("{}: {}", path, err))?;
+        if manifest.version != DERIVATIVES_VERSION {
+            return Err(format!("{}: unsupported derivatives version {}, expected {}", path, manifest.version, DERIVATIVES_VERSION).into());
+        }
+        Ok(manifest)
+    }
+
+    pub fn save(&self, dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+        write_atomic(&format!("{}/derivatives.json", dir), serde_json::to_string_pretty(self)?.as_bytes())
+    }
+}
+
+/// Makes every size in `formats` for each image in the mirror at `dir`.
+/// Derivatives already made from the same source image are kept, so only new
+/// and changed images get encoded.
+pub fn generate_derivatives(dir: &str, formats: &[DerivativeFormat]) -> Result<DerivativeSummary, Box<dyn std::error::Error>> {
+    let mirror = Manifest::load(dir)?;
+    let mut manifest = DerivativeManifest::load(dir)?;
+
+    for size in ImageSize::ALL {
+        fs::create_dir_all(format!("{}/{}", dir, label(&size)))?;
+    }
+
+    let mut summary = DerivativeSummary::default();
+    let mut pending: Vec<(&str, &ImageEntry, Vec<Derivative>)> = Vec::new();
+
+    for (image_name, entry) in &mirror.images {
+        // Keep what's still on disk and was made from this exact image
+        let mut done: Vec<Derivative> = manifest.images.get(image_name)
+            .filter(|set| set.source_sha256 == entry.sha256)
+            .map(|set| set.derivatives.clone())
+            .unwrap_or_default();
+        done.retain(|derivative| Path::new(&format!("{}/{}", dir, derivative.file)).exists());
+
+        let missing = ImageSize::ALL.iter()
+            .flat_map(|size| formats.iter().map(move |format| (*size, *format)))
+            .any(|(size, format)| !done.iter().any(|derivative| derivative.size == size && derivative.format == format));
+
+        if missing {
+            pending.push((image_name, entry, done));
+        } else {
+            summary.up_to_date += 1;
+        }
+    }
+
+    // Encoding is CPU bound, so split the work over every core
+    let workers = thread::available_parallelism().map(|count| count.get()).unwrap_or(1);
+    let results = Mutex::new(Vec::new());
+    let chunk_size = pending.len().div_ceil(workers).max(1);
+
+    thread::scope(|scope| {
+        for chunk in pending.chunks(chunk_size) {
+            let results = &results;
+            scope.spawn(move || {
+                for (image_name, entry, done) in chunk {
+                    let result = derive(dir, image_name, entry, done, formats);
+                    results.lock().unwrap().push((image_name.to_string(), entry.sha256.clone(), result));
+                }
+            });
+        }
+    });
+
+    for (image_name, source_sha256, result) in results.into_inner()? {
+        match result {
+            Ok(mut derivatives) => {
+                derivatives.sort_by_key(|derivative| (derivative.size, derivative.format));
+                manifest.images.insert(image_name, DerivativeSet { source_sha256, derivatives });
+                summary.generated += 1;
+            }
+            Err(err) => summary.failures.push((image_name, err.to_string())),
+        }
+    }
+
+    summary.failures.sort();
+    manifest.save(dir)?;
+    Ok(summary)
+}
+
+// Encodes whatever `done` doesn't have yet, returning the full set
+fn derive(
+    dir: &str,
+    image_name: &str,
+    entry: &ImageEntry,
+    done: &[Derivative],
+    formats: &[DerivativeFormat],
+) -> Result<Vec<Derivative>, Box<dyn std::error::Error + Send + Sync>> {
+    let source = image::open(format!("{}/{}", dir, entry.file))?;
+    let mut derivatives = done.to_vec();
+
+    for size in ImageSize::ALL {
+        // Never scale up, small source images are used as they are
+        let resized = match size.width() {
+            Some(width) if width < source.width() => source.resize(width, u32::MAX, FilterType::Lanczos3),
+            _ => source.clone(),
+        };
+
+        for format in formats {
+            if done.iter().any(|derivative| derivative.size == size && derivative.format == *format) {
+                continue;
+            }
+
+            let content = encode(&resized, *format)?;
+            let file = derivative_file(image_name, size, *format);
+            write_atomic(&format!("{}/{}", dir, file), &content).map_err(|err| err.to_string())?;
+
+            derivatives.push(Derivative {
+                size,
+                format: *format,
+                file,
+                width: resized.width(),
+                height: resized.height(),
+                bytes: content.len() as u64,
+            });
+        }
+    }
+
+    Ok(derivatives)
+}
+
+fn encode(image: &DynamicImage, format: DerivativeFormat) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut content = Vec::new();
+
+    match format {
+        DerivativeFormat::Webp => image.to_rgba8().write_with_encoder(WebPEncoder::new_lossless(&mut content))?,
+        DerivativeFormat::Avif => image.to_rgba8().write_with_encoder(AvifEncoder::new_with_speed_quality(&mut content, AVIF_SPEED, AVIF_QUALITY))?,
+        // JPEG has no alpha channel
+        DerivativeFormat::Jpeg => image.to_rgb8().write_with_encoder(JpegEncoder::new_with_quality(&mut content, JPEG_QUALITY))?,
+    }
+
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::images::MANIFEST_VERSION;
+    use std::io::Cursor;
+
+    // A 600x300 PNG mirrored as OP01-001
+    fn mirror_dir() -> String {
+        let dir = std::env::temp_dir().join(format!("derivatives-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap().to_string();
+
+        let image = image::RgbImage::from_fn(600, 300, |x, y| image::Rgb([(x % 256) as u8, (y % 256) as u8, 64]));
+        let mut png = Cursor::new(Vec::new());
+        image.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        fs::write(format!("{}/OP01-001.png", dir), png.get_ref()).unwrap();
+
+        let entry = ImageEntry {
+            url: "https://en.onepiece-cardgame.com/images/cardlist/card/OP01-001.png".to_string(),
+            file: "OP01-001.png".to_string(),
+            bytes: png.get_ref().len() as u64,
+            width: 600,
+            height: 300,
+            sha256: crate::sha256_hex(png.get_ref()),
+            etag: None,
+            last_modified: None,
+            perceptual_hash: None,
+        };
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            images: BTreeMap::from([("OP01-001".to_string(), entry)]),
+            failures: Vec::new(),
+            changes: Vec::new(),
+        };
+        manifest.save(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn makes_every_size_and_records_it() {
+        let dir = mirror_dir();
+        let formats = [DerivativeFormat::Jpeg, DerivativeFormat::Webp];
+
+        let summary = generate_derivatives(&dir, &formats).unwrap();
+        assert_eq!((summary.generated, summary.up_to_date, summary.failures.len()), (1, 0, 0));
+
+        let manifest = DerivativeManifest::load(&dir).unwrap();
+        let derivatives = &manifest.images["OP01-001"].derivatives;
+        let made: Vec<(ImageSize, DerivativeFormat, &str, u32, u32)> = derivatives.iter()
+            .map(|derivative| (derivative.size, derivative.format, derivative.file.as_str(), derivative.width, derivative.height))
+            .collect();
+        assert_eq!(made, vec![
+            (ImageSize::Thumb, DerivativeFormat::Webp, "thumb/OP01-001.webp", 200, 100),
+            (ImageSize::Thumb, DerivativeFormat::Jpeg, "thumb/OP01-001.jpg", 200, 100),
+            (ImageSize::Medium, DerivativeFormat::Webp, "medium/OP01-001.webp", 480, 240),
+            (ImageSize::Medium, DerivativeFormat::Jpeg, "medium/OP01-001.jpg", 480, 240),
+            (ImageSize::Full, DerivativeFormat::Webp, "full/OP01-001.webp", 600, 300),
+            (ImageSize::Full, DerivativeFormat::Jpeg, "full/OP01-001.jpg", 600, 300),
+        ]);
+
+        for derivative in derivatives {
+            let path = format!("{}/{}", dir, derivative.file);
+            assert_eq!(fs::metadata(&path).unwrap().len(), derivative.bytes);
+            let decoded = image::open(&path).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (derivative.width, derivative.height));
+        }
+
+        // Nothing left to do for the same source image
+        let summary = generate_derivatives(&dir, &formats).unwrap();
+        assert_eq!((summary.generated, summary.up_to_date), (0, 1));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
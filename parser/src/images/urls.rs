@@ -0,0 +1,219 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+};
+
+use super::{
+    derivatives::{DerivativeManifest, ImageSize},
+    extension, CardImages,
+};
+use crate::{label, Card};
+
+// Where card image URLs point, e.g. our own CDN instead of the official site
+const IMAGE_URLS: &str = include_str!("../../data/image-urls.json");
+
+/// Bump when the layout of the image URLs file changes
+pub const IMAGE_URLS_VERSION: u32 = 1;
+
+/// Where each region's images are mirrored unless `--image-dir` says otherwise
+pub const DEFAULT_IMAGE_DIR: &str = "../images/{region}";
+
+// `{size}` is only known for the `images` template
+const PLACEHOLDERS: [&str; 5] = ["cdn", "region", "image_name", "official_url", "ext"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageUrlConfig {
+    pub version: u32,
+    /// Base URL filled in for `{cdn}`
+    #[serde(default)]
+    pub cdn: Option<String>,
+    /// Templates for every region without its own
+    pub default: ImageTemplates,
+    #[serde(default)]
+    pub regions: BTreeMap<String, ImageTemplates>,
+    /// The image mirror, with `{region}` filled in, set from `--image-dir`
+    #[serde(skip)]
+    pub image_dir: Option<String>,
+}
+
+/// Templates like `{cdn}/{region}/{image_name}.webp`, with `{cdn}`, `{region}`,
+/// `{image_name}`, `{official_url}` and `{ext}` (the official file's extension)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageTemplates {
+    /// For `image_url`
+    pub image_url: String,
+    /// For each size in `images`, which can also use `{size}` (thumb, medium or full)
+    pub images: String,
+}
+
+/// The templates for one region, checked and ready to fill in
+#[derive(Debug, Clone)]
+pub struct ImageUrls {
+    cdn: String,
+    region: String,
+    templates: ImageTemplates,
+    /// Files listed in the region mirror's `derivatives.json`, keyed by image_name
+    derivatives: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ImageUrlConfig {
+    /// The templates bundled from `data/image-urls.json`
+    pub fn bundled() -> ImageUrlConfig {
+        ImageUrlConfig::parse(IMAGE_URLS).expect("data/image-urls.json is valid")
+    }
+
+    pub fn from_file(path: &str) -> Result<ImageUrlConfig, Box<dyn std::error::Error>> {
+        ImageUrlConfig::parse(&fs::read_to_string(path)?).map_err(|err| format!("{}: {}", path, err).into())
+    }
+
+    fn parse(content: &str) -> Result<ImageUrlConfig, Box<dyn std::error::Error>> {
+        let config: ImageUrlConfig = serde_json::from_str(content)?;
+
+        if config.version != IMAGE_URLS_VERSION {
+            return Err(format!("Unsupported image URLs version {}, expected {}", config.version, IMAGE_URLS_VERSION).into());
+        }
+
+        for templates in config.regions.values().chain([&config.default]) {
+            check_template(&templates.image_url, false)?;
+            check_template(&templates.images, true)?;
+        }
+
+        Ok(config)
+    }
+
+    /// Swaps in another CDN base URL, e.g. from `--cdn`
+    pub fn with_cdn(mut self, cdn: Option<String>) -> ImageUrlConfig {
+        if cdn.is_some() {
+            self.cdn = cdn;
+        }
+        self
+    }
+
+    /// Swaps in another image mirror, e.g. from `--image-dir`
+    pub fn with_image_dir(mut self, image_dir: Option<String>) -> ImageUrlConfig {
+        if image_dir.is_some() {
+            self.image_dir = image_dir;
+        }
+        self
+    }
+
+    /// The image mirror of `region`, which has the files `images` points at
+    pub fn image_dir(&self, region: &str) -> String {
+        self.image_dir.as_deref().unwrap_or(DEFAULT_IMAGE_DIR).replace("{region}", region)
+    }
+
+    pub fn for_region(&self, region: &str) -> Result<ImageUrls, Box<dyn std::error::Error>> {
+        let templates = self.regions.get(region).unwrap_or(&self.default).clone();
+
+        let uses_cdn = templates.image_url.contains("{cdn}") || templates.images.contains("{cdn}");
+        let cdn = match &self.cdn {
+            Some(cdn) => cdn.trim_end_matches('/').to_string(),
+            None if uses_cdn => return Err(format!("The image URL templates for {} use {{cdn}}, set cdn or pass --cdn", region).into()),
+            None => String::new(),
+        };
+
+        // Relative `images` paths only point somewhere once `derivatives` has made the files
+        let derivatives = DerivativeManifest::load(&self.image_dir(region))?.images.into_iter()
+            .map(|(image_name, set)| (image_name, set.derivatives.into_iter().map(|derivative| derivative.file).collect()))
+            .collect();
+
+        Ok(ImageUrls { cdn, region: region.to_string(), templates, derivatives })
+    }
+}
+
+impl ImageUrls {
+    /// Fills in `image_url` and `images` from the templates. The official URL
+    /// is kept in `official_image_url`, cards saved before it existed had it
+    /// in `image_url`. `images` is only set when the template points at the
+    /// CDN or every size it names is in the region's derivatives manifest.
+    pub fn apply(&self, card: &mut Card) {
+        if card.official_image_url.is_empty() {
+            card.official_image_url = card.image_url.clone();
+        }
+
+        card.image_url = self.render(&self.templates.image_url, card, None);
+        let images = CardImages {
+            thumb: self.render(&self.templates.images, card, Some(ImageSize::Thumb)),
+            medium: self.render(&self.templates.images, card, Some(ImageSize::Medium)),
+            full: self.render(&self.templates.images, card, Some(ImageSize::Full)),
+        };
+
+        let derived = self.derivatives.get(&card.image_name)
+            .is_some_and(|files| [&images.thumb, &images.medium, &images.full].iter().all(|file| files.contains(*file)));
+        card.images = (self.cdn_images() || derived).then_some(images);
+    }
+
+    /// Whether `images` are CDN URLs rather than paths into the local mirror
+    pub fn cdn_images(&self) -> bool {
+        self.templates.images.contains("{cdn}")
+    }
+
+    fn render(&self, template: &str, card: &Card, size: Option<ImageSize>) -> String {
+        let rendered = template
+            .replace("{cdn}", &self.cdn)
+            .replace("{region}", &self.region)
+            .replace("{image_name}", &card.image_name)
+            .replace("{ext}", extension(&card.official_image_url))
+            .replace("{official_url}", &card.official_image_url);
+
+        match size {
+            Some(size) => rendered.replace("{size}", &label(&size)),
+            None => rendered,
+        }
+    }
+}
+
+fn check_template(template: &str, with_size: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let placeholder_pattern = Regex::new(r"\{([^{}]*)\}").unwrap();
+
+    for captures in placeholder_pattern.captures_iter(template) {
+        let name = &captures[1];
+        let known = PLACEHOLDERS.contains(&name) || (with_size && name == "size");
+        if !known {
+            return Err(format!("Unknown placeholder {{{}}} in {}", name, template).into());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn image_urls(images: &str, derived: &[&str]) -> ImageUrls {
+        ImageUrls {
+            cdn: "https://cdn.example.com".to_string(),
+            region: "en".to_string(),
+            templates: ImageTemplates { image_url: "{official_url}".to_string(), images: images.to_string() },
+            derivatives: BTreeMap::from([("OP01-001".to_string(), derived.iter().map(|file| file.to_string()).collect())]),
+        }
+    }
+
+    #[test]
+    fn images_need_every_derivative() {
+        let mut card = crate::test_card(json!({}));
+        image_urls("{size}/{image_name}.jpg", &["thumb/OP01-001.jpg", "medium/OP01-001.jpg"]).apply(&mut card);
+        assert_eq!(card.images, None);
+
+        image_urls("{size}/{image_name}.jpg", &["thumb/OP01-001.jpg", "medium/OP01-001.jpg", "full/OP01-001.jpg"]).apply(&mut card);
+        assert_eq!(card.images.unwrap().full, "full/OP01-001.jpg");
+    }
+
+    #[test]
+    fn cdn_images_are_always_set() {
+        let mut card = crate::test_card(json!({}));
+        image_urls("{cdn}/{region}/{size}/{image_name}.webp", &[]).apply(&mut card);
+        assert_eq!(card.images.unwrap().thumb, "https://cdn.example.com/en/thumb/OP01-001.webp");
+    }
+
+    #[test]
+    fn image_dir_fills_in_the_region() {
+        assert_eq!(ImageUrlConfig::bundled().image_dir("en"), "../images/en");
+        assert_eq!(ImageUrlConfig::bundled().with_image_dir(Some("/srv/{region}/mirror".to_string())).image_dir("jp"), "/srv/jp/mirror");
+        assert_eq!(ImageUrlConfig::bundled().with_image_dir(None).image_dir("jp"), "../images/jp");
+    }
+}
@@ -0,0 +1,281 @@
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{fs, time::SystemTime};
+
+use crate::deck::MAX_COPIES;
+
+// The official ban/restrict list history, each update is the full list from its date on
+const BAN_LIST: &str = include_str!("../data/ban-list.json");
+
+/// Bump when the layout of the ban list file changes
+pub const BAN_LIST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BanList {
+    pub version: u32,
+    pub updates: Vec<BanListUpdate>,
+}
+
+/// The ban list as it stood from `effective_date` until the next update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BanListUpdate {
+    /// YYYY-MM-DD
+    pub effective_date: String,
+    #[serde(default)]
+    pub banned: Vec<String>,
+    #[serde(default)]
+    pub restricted: Vec<Restriction>,
+    /// Cards that are fine on their own but can't be in the same deck
+    #[serde(default)]
+    pub banned_pairs: Vec<[String; 2]>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Restriction {
+    pub card_number: String,
+    pub max_copies: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LegalityStatus {
+    #[default]
+    Legal,
+    Restricted,
+    Banned,
+}
+
+/// Where a card stands on the ban list in effect when the data was written
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+pub struct Legality {
+    pub status: LegalityStatus,
+    /// Copies allowed in a deck, 0 when banned
+    pub max_copies: u32,
+    /// Card numbers this card can't share a deck with
+    pub banned_with: Vec<String>,
+    /// Effective date of the ban list this comes from, null before the first one
+    pub ban_list_date: Option<String>,
+}
+
+impl Default for Legality {
+    fn default() -> Self {
+        Legality {
+            status: LegalityStatus::Legal,
+            max_copies: MAX_COPIES,
+            banned_with: Vec::new(),
+            ban_list_date: None,
+        }
+    }
+}
+
+impl BanList {
+    /// The ban list bundled from `data/ban-list.json`
+    pub fn bundled() -> BanList {
+        BanList::parse(BAN_LIST).expect("data/ban-list.json is valid")
+    }
+
+    pub fn from_file(path: &str) -> Result<BanList, Box<dyn std::error::Error>> {
+        BanList::parse(&fs::read_to_string(path)?).map_err(|err| format!("{}: {}", path, err).into())
+    }
+
+    fn parse(content: &str) -> Result<BanList, Box<dyn std::error::Error>> {
+        let mut ban_list: BanList = serde_json::from_str(content)?;
+
+        if ban_list.version != BAN_LIST_VERSION {
+            return Err(format!("Unsupported ban list version {}, expected {}", ban_list.version, BAN_LIST_VERSION).into());
+        }
+
+        for update in &ban_list.updates {
+            check_date(&update.effective_date)?;
+        }
+
+        ban_list.updates.sort_by(|a, b| a.effective_date.cmp(&b.effective_date));
+        Ok(ban_list)
+    }
+
+    /// The list in effect on `date` (YYYY-MM-DD), None before the first update
+    pub fn as_of(&self, date: &str) -> Result<Option<&BanListUpdate>, Box<dyn std::error::Error>> {
+        check_date(date)?;
+        Ok(self.updates.iter().rev().find(|update| update.effective_date.as_str() <= date))
+    }
+}
+
+impl BanListUpdate {
+    pub fn legality(&self, card_number: &str) -> Legality {
+        let banned_with: Vec<String> = self.banned_pairs.iter()
+            .filter_map(|[a, b]| {
+                if a == card_number {
+                    Some(b.clone())
+                } else if b == card_number {
+                    Some(a.clone())
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let restriction = self.restricted.iter().find(|restriction| restriction.card_number == card_number);
+
+        let (status, max_copies) = if self.banned.iter().any(|banned| banned == card_number) {
+            (LegalityStatus::Banned, 0)
+        } else if let Some(restriction) = restriction {
+            (LegalityStatus::Restricted, restriction.max_copies)
+        } else {
+            (LegalityStatus::Legal, MAX_COPIES)
+        };
+
+        Legality {
+            status,
+            max_copies,
+            banned_with,
+            ban_list_date: Some(self.effective_date.clone()),
+        }
+    }
+}
+
+/// Legality of a card on an optional ban list, legal everywhere without one
+pub fn legality(ban_list: Option<&BanListUpdate>, card_number: &str) -> Legality {
+    ban_list.map(|update| update.legality(card_number)).unwrap_or_default()
+}
+
+/// Checks that `date` is a real calendar date written as YYYY-MM-DD
+pub fn check_date(date: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let date_pattern = Regex::new(r"^(\d{4})-(\d{2})-(\d{2})$").unwrap();
+    let Some(captures) = date_pattern.captures(date) else {
+        return Err(format!("Expected a YYYY-MM-DD date, got {}", date).into());
+    };
+
+    let year: u32 = captures[1].parse()?;
+    let month: u32 = captures[2].parse()?;
+    let day: u32 = captures[3].parse()?;
+
+    let is_leap_year = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year => 29,
+        2 => 28,
+        _ => return Err(format!("{} has no month {}", date, month).into()),
+    };
+
+    if day == 0 || day > days_in_month {
+        return Err(format!("{} has no day {}", date, day).into());
+    }
+
+    Ok(())
+}
+
+/// Today's UTC date as YYYY-MM-DD
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    // Days since 1970-01-01 to a civil date, from Howard Hinnant's date algorithms
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ban_list() -> BanList {
+        // Out of order on purpose, parse sorts them
+        BanList::parse(r#"{
+            "version": 1,
+            "updates": [
+                { "effective_date": "2024-11-09", "banned": ["OP03-040"], "restricted": [{ "card_number": "OP01-016", "max_copies": 1 }] },
+                { "effective_date": "2024-03-08", "banned": ["OP02-024"], "banned_pairs": [["OP05-001", "OP05-119"]] },
+                { "effective_date": "2025-02-28" }
+            ]
+        }"#).unwrap()
+    }
+
+    fn effective_date(date: &str) -> Option<String> {
+        ban_list().as_of(date).unwrap().map(|update| update.effective_date.clone())
+    }
+
+    #[test]
+    fn as_of_picks_the_update_in_effect() {
+        assert_eq!(effective_date("2024-03-07"), None);
+        assert_eq!(effective_date("2024-03-08").as_deref(), Some("2024-03-08"));
+        assert_eq!(effective_date("2024-03-09").as_deref(), Some("2024-03-08"));
+        assert_eq!(effective_date("2024-11-08").as_deref(), Some("2024-03-08"));
+        assert_eq!(effective_date("2024-11-09").as_deref(), Some("2024-11-09"));
+        assert_eq!(effective_date("2025-02-27").as_deref(), Some("2024-11-09"));
+        assert_eq!(effective_date("2025-02-28").as_deref(), Some("2025-02-28"));
+        assert_eq!(effective_date("2030-01-01").as_deref(), Some("2025-02-28"));
+    }
+
+    #[test]
+    fn as_of_rejects_bad_dates() {
+        assert!(ban_list().as_of("2024-3-8").is_err());
+        assert!(ban_list().as_of("2024-02-30").is_err());
+    }
+
+    #[test]
+    fn each_update_is_the_full_list() {
+        let ban_list = ban_list();
+        let march = ban_list.as_of("2024-03-08").unwrap();
+        let november = ban_list.as_of("2024-11-09").unwrap();
+        let february = ban_list.as_of("2025-02-28").unwrap();
+
+        assert_eq!(legality(march, "OP02-024").status, LegalityStatus::Banned);
+        assert_eq!(legality(november, "OP02-024").status, LegalityStatus::Legal);
+        assert_eq!(legality(february, "OP03-040").status, LegalityStatus::Legal);
+        assert_eq!(legality(None, "OP02-024"), Legality::default());
+    }
+
+    #[test]
+    fn legality_of_a_card() {
+        let ban_list = ban_list();
+        let march = ban_list.as_of("2024-06-01").unwrap();
+        let november = ban_list.as_of("2024-12-01").unwrap();
+
+        assert_eq!(legality(november, "OP03-040"), Legality {
+            status: LegalityStatus::Banned,
+            max_copies: 0,
+            banned_with: vec![],
+            ban_list_date: Some("2024-11-09".to_string()),
+        });
+        assert_eq!(legality(november, "OP01-016").status, LegalityStatus::Restricted);
+        assert_eq!(legality(november, "OP01-016").max_copies, 1);
+        assert_eq!(legality(march, "OP05-119").banned_with, vec!["OP05-001"]);
+        assert_eq!(legality(march, "OP05-001").banned_with, vec!["OP05-119"]);
+        assert_eq!(legality(march, "OP05-001").status, LegalityStatus::Legal);
+    }
+
+    #[test]
+    fn ban_list_files_are_checked() {
+        assert!(BanList::parse(r#"{ "version": 2, "updates": [] }"#).is_err());
+        assert!(BanList::parse(r#"{ "version": 1, "updates": [{ "effective_date": "2024-13-01" }] }"#).is_err());
+        assert!(BanList::bundled().updates.windows(2).all(|pair| pair[0].effective_date < pair[1].effective_date));
+    }
+
+    #[test]
+    fn calendar_dates() {
+        for date in ["2024-01-01", "2024-01-31", "2024-02-29", "2000-02-29", "2023-02-28", "2024-04-30", "2024-12-31"] {
+            assert!(check_date(date).is_ok(), "{}", date);
+        }
+        for date in ["2025-13-45", "2024-00-10", "2024-01-00", "2024-01-32", "2023-02-29", "1900-02-29", "2024-04-31", "2024-1-01", "20240101", "2024-01-01 ", ""] {
+            assert!(check_date(date).is_err(), "{}", date);
+        }
+    }
+
+    #[test]
+    fn today_is_a_date() {
+        assert!(check_date(&today()).is_ok());
+    }
+}
@@ -1,19 +1,62 @@
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use scraper::{Html, Selector, ElementRef};
-use std::{fs, collections::HashSet, thread, time::Duration, cmp::Ordering};
-use reqwest::Client;
+use std::{fs, fmt, collections::{BTreeMap, HashSet}, thread, time::Duration, cmp::Ordering};
+use reqwest::{Client, Url};
 use serde_json::json;
 use html_escape::decode_html_entities;
+use clap::{Parser, Subcommand};
+use sha2::{Digest, Sha256};
 
+mod api;
+mod deck;
+mod export;
+mod images;
+mod legality;
+mod names;
+mod query;
+mod rotation;
+mod schema;
+mod search;
+mod server;
+mod synergy;
+
+use deck::DeckFormat;
+use export::ExportFormat;
+use images::{CardImages, DerivativeFormat, ImageUrlConfig, ImageUrls};
+use legality::{BanList, Legality};
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 enum CardType {
     LEADER,
     STAGE,
     EVENT,
     CHARACTER,
+    #[serde(rename = "DON!!")]
+    DON,
+    // Card types the site adds later keep their raw text
+    #[serde(untagged)]
+    UNKNOWN(String),
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+// schemars doesn't understand untagged variants, so the raw string is spelled out
+impl JsonSchema for CardType {
+    fn schema_name() -> String {
+        "CardType".to_string()
+    }
+
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        serde_json::from_value(json!({
+            "anyOf": [
+                { "type": "string", "enum": ["LEADER", "STAGE", "EVENT", "CHARACTER", "DON!!"] },
+                { "type": "string" },
+            ]
+        })).unwrap()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 enum Rarity {
     #[serde(rename = "C")]
     Common,
@@ -33,9 +76,11 @@ enum Rarity {
     Promo,
     #[serde(rename = "TR")]
     TreasureRare,
+    #[serde(rename = "-")]
+    Unrated,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum Color {
     Red,
     Blue,
@@ -87,26 +132,91 @@ enum Effect {
     YourTurn,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Eq)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, Eq)]
 struct Card {
     card_name: String,
+    #[serde(default)]
+    name_key: String,
     card_number: String,
     rarity: Rarity,
     is_alternate_art: bool,
     card_type: CardType,
+    /// From the image URL templates in data/image-urls.json, the official image by default
     image_url: String,
+    /// The image on the official site, as linked from the card list
+    #[serde(default)]
+    official_image_url: String,
     life: String,
     cost: String,
     attributes: Vec<String>,
     power: String,
     counter: String,
     block_icon: String,
+    /// Block icon of the newest printing, which decides rotation
+    #[serde(default)]
+    effective_block_icon: String,
     colors: Vec<Color>,
     types: Vec<String>,
     effects: Option<String>,
     card_effects: Vec<String>,
+    #[serde(default)]
+    referenced_types: Vec<String>,
+    #[serde(default)]
+    referenced_names: Vec<String>,
+    #[serde(default)]
+    referenced_card_numbers: Vec<String>,
+    #[serde(default)]
+    referenced_attributes: Vec<String>,
+    #[serde(default)]
+    counter_value: Option<u32>,
+    #[serde(default)]
+    is_counter_event: bool,
+    #[serde(default)]
+    counter_power_boost: Option<u32>,
+    #[serde(default)]
+    trigger: Option<String>,
+    #[serde(default)]
+    has_trigger: bool,
+    #[serde(default)]
+    trigger_details: Option<TriggerDetails>,
+    #[serde(default)]
+    legality: Legality,
+    /// Ids of the formats in data/formats.json this card is legal in
+    #[serde(default)]
+    legal_formats: Vec<String>,
     card_sets: String,
     image_name: String,
+    /// Sizes made by the `derivatives` command, from the image URL templates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    images: Option<CardImages>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq)]
+struct TriggerDetails {
+    cost: Option<String>,
+    effect: String,
+    plays_this_card: bool,
+    activates: Option<String>,
+}
+
+impl TriggerDetails {
+    fn parse(trigger: &str) -> Self {
+        // "DON!! −1 (reminder text): Play this card." has a cost before the colon
+        let cost_pattern = regex::Regex::new(r"^([^\[:]+?)(?: \([^)]*\))?:\s*(.+)$").unwrap();
+        let activate_pattern = regex::Regex::new(r"Activate this card's (\[[^\]]+\]) effect").unwrap();
+
+        let (cost, effect) = match cost_pattern.captures(trigger) {
+            Some(captures) => (Some(captures[1].trim().to_string()), captures[2].trim().to_string()),
+            None => (None, trigger.trim().to_string()),
+        };
+
+        TriggerDetails {
+            plays_this_card: effect.to_lowercase().contains("play this card"),
+            activates: activate_pattern.captures(&effect).map(|captures| captures[1].to_string()),
+            cost,
+            effect,
+        }
+    }
 }
 
 impl PartialEq for Card {
@@ -150,6 +260,213 @@ impl Ord for Card {
     }
 }
 
+/// Type specific view of a card, only the fields that mean something for
+/// that card type are present and numbers are actual numbers instead of "-"
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(tag = "card_type")]
+enum CardDetails {
+    #[serde(rename = "LEADER")]
+    Leader {
+        life: Option<u32>,
+        power: Option<u32>,
+        attributes: Vec<String>,
+    },
+    #[serde(rename = "CHARACTER")]
+    Character {
+        cost: Option<u32>,
+        power: Option<u32>,
+        counter: Option<u32>,
+        attributes: Vec<String>,
+    },
+    #[serde(rename = "EVENT")]
+    Event {
+        cost: Option<u32>,
+        is_counter_event: bool,
+        counter_power_boost: Option<u32>,
+    },
+    #[serde(rename = "STAGE")]
+    Stage {
+        cost: Option<u32>,
+    },
+    #[serde(rename = "DON!!")]
+    Don,
+    /// A card type the site added later, with its raw text like `CardType`
+    #[serde(untagged)]
+    Unknown {
+        #[schemars(schema_with = "unknown_card_type_schema")]
+        card_type: String,
+    },
+}
+
+// Any card type but the known ones, which would match two variants otherwise
+fn unknown_card_type_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    serde_json::from_value(json!({
+        "type": "string",
+        "not": { "enum": ["LEADER", "STAGE", "EVENT", "CHARACTER", "DON!!"] },
+    })).unwrap()
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+struct TypedCard {
+    card_name: String,
+    name_key: String,
+    card_number: String,
+    rarity: Rarity,
+    is_alternate_art: bool,
+    image_url: String,
+    official_image_url: String,
+    block_icon: String,
+    effective_block_icon: String,
+    colors: Vec<Color>,
+    types: Vec<String>,
+    effects: Option<String>,
+    card_effects: Vec<String>,
+    referenced_types: Vec<String>,
+    referenced_names: Vec<String>,
+    referenced_card_numbers: Vec<String>,
+    referenced_attributes: Vec<String>,
+    trigger: Option<String>,
+    has_trigger: bool,
+    trigger_details: Option<TriggerDetails>,
+    legality: Legality,
+    legal_formats: Vec<String>,
+    card_sets: String,
+    image_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    images: Option<CardImages>,
+    #[serde(flatten)]
+    details: CardDetails,
+}
+
+impl Card {
+    fn details(&self) -> CardDetails {
+        let attributes: Vec<String> = self.attributes.iter()
+            .filter(|attribute| !is_sentinel(attribute))
+            .cloned()
+            .collect();
+
+        match self.card_type {
+            CardType::LEADER => CardDetails::Leader {
+                life: parse_number(&self.life),
+                power: parse_number(&self.power),
+                attributes,
+            },
+            CardType::CHARACTER => CardDetails::Character {
+                cost: parse_number(&self.cost),
+                power: parse_number(&self.power),
+                counter: parse_number(&self.counter),
+                attributes,
+            },
+            CardType::EVENT => CardDetails::Event {
+                cost: parse_number(&self.cost),
+                is_counter_event: self.is_counter_event,
+                counter_power_boost: self.counter_power_boost,
+            },
+            CardType::STAGE => CardDetails::Stage {
+                cost: parse_number(&self.cost),
+            },
+            CardType::DON => CardDetails::Don,
+            CardType::UNKNOWN(ref card_type) => CardDetails::Unknown { card_type: card_type.clone() },
+        }
+    }
+
+    fn compute_counter_info(&mut self) {
+        self.counter_value = match self.card_type {
+            CardType::CHARACTER => parse_number(&self.counter),
+            _ => None,
+        };
+
+        let counter_text = self.effects.as_deref()
+            .filter(|_| self.card_type == CardType::EVENT)
+            .and_then(|effects| effects.split_once("[Counter]"))
+            .map(|(_, rest)| rest.split("[Trigger]").next().unwrap_or_default());
+
+        self.is_counter_event = counter_text.is_some();
+
+        // The first boost in the [Counter] text is the one the card always gives
+        self.counter_power_boost = counter_text.and_then(|text| {
+            let boost_pattern = regex::Regex::new(r"\+(\d+) power").unwrap();
+            boost_pattern.captures(text)
+                .and_then(|captures| captures[1].parse().ok())
+        });
+    }
+
+    fn compute_trigger_info(&mut self) {
+        // Cards saved before triggers were split out only have them appended to the effects
+        if self.trigger.is_none() {
+            let effects = self.effects.as_deref().unwrap_or_default();
+            self.trigger = trigger_label_index(effects)
+                .map(|idx| effects[idx + "[Trigger]".len()..].trim().to_string());
+        }
+
+        self.has_trigger = self.trigger.is_some();
+        self.trigger_details = self.trigger.as_deref().map(TriggerDetails::parse);
+    }
+
+    // The effect text without the trigger, which v2 has in its own field
+    fn effects_without_trigger(&self) -> Option<String> {
+        let effects = self.effects.as_deref()?;
+        let effects = match trigger_label_index(effects) {
+            Some(idx) => effects[..idx].trim(),
+            None => effects,
+        };
+
+        Some(effects.to_string()).filter(|effects| !effects.is_empty())
+    }
+}
+
+// Position of the [Trigger] label, one right after a word is a reference instead
+fn trigger_label_index(effects: &str) -> Option<usize> {
+    effects.rmatch_indices("[Trigger]")
+        .map(|(idx, _)| idx)
+        .find(|idx| !effects[..*idx].trim_end().ends_with(|c: char| c.is_alphabetic()))
+}
+
+impl From<&Card> for TypedCard {
+    fn from(card: &Card) -> Self {
+        TypedCard {
+            card_name: card.card_name.clone(),
+            name_key: card.name_key.clone(),
+            card_number: card.card_number.clone(),
+            rarity: card.rarity.clone(),
+            is_alternate_art: card.is_alternate_art,
+            image_url: card.image_url.clone(),
+            official_image_url: card.official_image_url.clone(),
+            block_icon: card.block_icon.clone(),
+            effective_block_icon: card.effective_block_icon.clone(),
+            colors: card.colors.clone(),
+            types: card.types.clone(),
+            effects: card.effects_without_trigger(),
+            card_effects: card.card_effects.iter()
+                .filter(|effect| !is_sentinel(effect))
+                .cloned()
+                .collect(),
+            referenced_types: card.referenced_types.clone(),
+            referenced_names: card.referenced_names.clone(),
+            referenced_card_numbers: card.referenced_card_numbers.clone(),
+            referenced_attributes: card.referenced_attributes.clone(),
+            trigger: card.trigger.clone(),
+            has_trigger: card.has_trigger,
+            trigger_details: card.trigger_details.clone(),
+            legality: card.legality.clone(),
+            legal_formats: card.legal_formats.clone(),
+            card_sets: card.card_sets.clone(),
+            image_name: card.image_name.clone(),
+            images: card.images.clone(),
+            details: card.details(),
+        }
+    }
+}
+
+// The site uses "-" (and sometimes nothing at all) for "does not apply"
+fn is_sentinel(value: &str) -> bool {
+    matches!(value.trim(), "" | "-")
+}
+
+fn parse_number(value: &str) -> Option<u32> {
+    value.trim().parse().ok()
+}
+
 #[derive(Debug)]
 struct SetInfo {
     set_type: String,
@@ -179,6 +496,54 @@ fn extract_set_info(card_sets: &str) -> SetInfo {
     }
 }
 
+// The set code from a card_sets string, e.g. "OP-01" or "OP-15-EB04"
+fn set_code(card_sets: &str) -> Option<String> {
+    let code_pattern = regex::Regex::new(r"\[([A-Z]+-\d+(?:-[A-Z]+\d+)?)\]").unwrap();
+    code_pattern.captures(card_sets).map(|captures| captures[1].to_string())
+}
+
+// Stable key for a set: its code when there is one, otherwise a slug of the name
+fn set_key(card_sets: &str) -> Option<String> {
+    if is_sentinel(card_sets) {
+        return None;
+    }
+
+    set_code(card_sets).or_else(|| {
+        let slug = slugify(card_sets);
+        (!slug.is_empty()).then_some(slug)
+    })
+}
+
+// "Straw Hat Crew" -> "straw-hat-crew", safe to use as a file name
+fn slugify(value: &str) -> String {
+    value
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// One card per card number, using the regular art printing when there is one
+fn unique_cards(cards: &[Card]) -> Vec<&Card> {
+    let mut unique: BTreeMap<&str, &Card> = BTreeMap::new();
+    for card in cards {
+        let replace = unique.get(card.card_number.as_str())
+            .is_none_or(|existing| existing.is_alternate_art && !card.is_alternate_art);
+        if replace {
+            unique.insert(&card.card_number, card);
+        }
+    }
+    unique.into_values().collect()
+}
+
 fn compare_set_types(type1: &str, type2: &str) -> Ordering {
     // Define priority order for set types
     let priority = |set_type: &str| {
@@ -241,8 +606,347 @@ struct CardSource {
     region: &'static str,
 }
 
+#[derive(Debug, Parser)]
+#[command(about = "Scrapes the One Piece card list into json/")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Image URL templates to use instead of the bundled data/image-urls.json
+    #[arg(long, global = true)]
+    image_urls: Option<String>,
+    /// Base URL for {cdn} in the image URL templates
+    #[arg(long, global = true)]
+    cdn: Option<String>,
+    /// The image mirror `images` on each card is checked against, defaults to ../images/{region}
+    #[arg(long, global = true)]
+    image_dir: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Scrape the official card lists and update json/ (the default)
+    Scrape {
+        /// Extra formats to write next to the JSON, e.g. csv,ndjson,sqlite
+        #[arg(long, value_delimiter = ',')]
+        export: Vec<ExportFormat>,
+    },
+    /// Write extra formats from the existing json/{region}/cards.json
+    Export {
+        #[arg(long, default_value = "en")]
+        region: String,
+        #[arg(long, value_delimiter = ',', required = true)]
+        formats: Vec<ExportFormat>,
+    },
+    /// Generate the static JSON API from json/{region}/cards.json
+    Api {
+        #[arg(long, default_value = "en")]
+        region: String,
+        /// Defaults to ../api/{region}
+        #[arg(long)]
+        out: Option<String>,
+        #[arg(long, default_value_t = api::DEFAULT_PER_PAGE)]
+        per_page: usize,
+    },
+    /// Serve json/{region}/cards.json over HTTP
+    Serve {
+        #[arg(long, default_value = "en")]
+        region: String,
+        #[arg(long, default_value_t = 3000)]
+        port: u16,
+    },
+    /// Search json/{region}/cards.json, e.g. `c:red t:character cost<=4 kw:blocker`
+    Search {
+        #[arg(long, default_value = "en")]
+        region: String,
+        /// Print the matching cards as JSON instead of one line each
+        #[arg(long)]
+        json: bool,
+        /// Every printing instead of one per card number
+        #[arg(long)]
+        all_printings: bool,
+        /// Ranked, typo tolerant text search over names, types and effects instead
+        #[arg(long, conflicts_with_all = ["json", "all_printings"])]
+        text: bool,
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        #[arg(required = true)]
+        query: Vec<String>,
+    },
+    /// Mirror the card images of json/{region}/cards.json into a local directory
+    Images {
+        #[arg(long, default_value = "en")]
+        region: String,
+        /// Defaults to --image-dir
+        #[arg(long)]
+        out: Option<String>,
+        /// Fetch from this base URL instead of the official site, e.g. a local test server
+        #[arg(long)]
+        base_url: Option<String>,
+        #[arg(long, default_value_t = images::DEFAULT_CONCURRENCY)]
+        concurrency: usize,
+        /// Only fetch images that aren't mirrored yet, without checking the others for changes
+        #[arg(long)]
+        missing_only: bool,
+    },
+    /// Make thumbnail, medium and full size copies of the images mirrored by `images`
+    Derivatives {
+        #[arg(long, default_value = "en")]
+        region: String,
+        /// The mirror directory, defaults to --image-dir
+        #[arg(long)]
+        out: Option<String>,
+        #[arg(long, value_delimiter = ',', default_value = "jpeg")]
+        formats: Vec<DerivativeFormat>,
+    },
+    /// Group shared arts and flag likely misclassified alternate arts in the image mirror
+    Art {
+        #[arg(long, default_value = "en")]
+        region: String,
+        /// The mirror directory, defaults to --image-dir
+        #[arg(long)]
+        out: Option<String>,
+        /// Most perceptual hash bits two images can differ in and still be the same art
+        #[arg(long, default_value_t = images::SAME_ART_DISTANCE)]
+        max_distance: u32,
+    },
+    /// Regenerate the JSON Schemas and index.d.ts from the output structs
+    Schema {
+        #[arg(long, default_value = "../json")]
+        out: String,
+    },
+    /// Work with deck lists
+    Deck {
+        #[command(subcommand)]
+        command: DeckCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DeckCommand {
+    /// Check a deck list against the deck construction rules
+    Validate {
+        /// Deck list file, or - for stdin
+        file: String,
+        /// Detected from the content when left out
+        #[arg(long)]
+        format: Option<DeckFormat>,
+        #[arg(long, default_value = "en")]
+        region: String,
+        /// Check against the ban list in effect on this YYYY-MM-DD date instead of today's
+        #[arg(long)]
+        as_of: Option<String>,
+        /// Ban list file to use instead of the bundled data/ban-list.json
+        #[arg(long)]
+        ban_list: Option<String>,
+        /// Also check block rotation for a format id, e.g. standard
+        #[arg(long)]
+        game_format: Option<String>,
+        /// Formats file to use instead of the bundled data/formats.json
+        #[arg(long)]
+        formats: Option<String>,
+        /// Print the violations as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Cost curve, counters, keywords and draw odds for a deck list
+    Stats {
+        /// Deck list file, or - for stdin
+        file: String,
+        /// Detected from the content when left out
+        #[arg(long)]
+        format: Option<DeckFormat>,
+        #[arg(long, default_value = "en")]
+        region: String,
+        /// Print the stats as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Convert a deck list between the text, JSON and deck code formats
+    Convert {
+        /// Deck list file, or - for stdin
+        file: String,
+        /// Detected from the content when left out
+        #[arg(long)]
+        from: Option<DeckFormat>,
+        #[arg(long)]
+        to: DeckFormat,
+        #[arg(long, default_value = "en")]
+        region: String,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    let image_urls = match cli.image_urls {
+        Some(path) => ImageUrlConfig::from_file(&path)?,
+        None => ImageUrlConfig::bundled(),
+    }.with_cdn(cli.cdn).with_image_dir(cli.image_dir);
+
+    match cli.command.unwrap_or(Command::Scrape { export: Vec::new() }) {
+        Command::Scrape { export } => scrape(&export, &image_urls).await,
+        Command::Export { region, formats } => {
+            let cards = load_cards(&region, &image_urls)?;
+            export::run(&formats, &cards, &format!("../json/{}", region))
+        }
+        Command::Api { region, out, per_page } => {
+            let cards = load_cards(&region, &image_urls)?;
+            let out = out.unwrap_or_else(|| format!("../api/{}", region));
+            api::generate(&cards, &region, &out, per_page)
+        }
+        Command::Serve { region, port } => {
+            let cards = load_cards(&region, &image_urls)?;
+            server::serve(cards, port).await
+        }
+        Command::Search { region, json, all_printings, text, limit, query } => {
+            let cards = load_cards(&region, &image_urls)?;
+
+            if text {
+                let index = search::SearchIndex::build(&cards);
+                for hit in index.search(&query.join(" "), limit) {
+                    println!("{:<14} {:>7.2}  {}", hit.card_number, hit.score, hit.card_name);
+                }
+                return Ok(());
+            }
+
+            let query = query::Query::parse(&query.join(" "))?;
+
+            let candidates: Vec<&Card> = if all_printings {
+                cards.iter().collect()
+            } else {
+                unique_cards(&cards)
+            };
+            let matching: Vec<&Card> = candidates.into_iter().filter(|card| query.matches(card)).collect();
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&matching)?);
+            } else {
+                for card in &matching {
+                    let colors: Vec<String> = card.colors.iter().map(label).collect();
+                    println!("{:<14} {:<10} {:<14} {}", card.card_number, label(&card.card_type), colors.join("/"), card.card_name);
+                }
+                eprintln!("{} cards", matching.len());
+            }
+            Ok(())
+        }
+        Command::Images { region, out, base_url, concurrency, missing_only } => {
+            let cards = load_existing_cards_from_output(&format!("../json/{}", region))?;
+            let out = out.unwrap_or_else(|| image_urls.image_dir(&region));
+            let summary = images::mirror(&cards, &out, &images::MirrorOptions { base_url, concurrency, missing_only }).await?;
+
+            println!("Downloaded {} ({} changed), unchanged {}, skipped {}, failed {}",
+                summary.downloaded, summary.changes.len(), summary.unchanged, summary.skipped, summary.failures.len());
+            for failure in &summary.failures {
+                println!("  {:?} {} {}: {}", failure.kind, failure.image_name, failure.url, failure.error);
+            }
+            for change in summary.changes.iter().filter(|change| change.art_changed) {
+                println!("  Art changed {} (distance {})", change.image_name, change.distance.map_or("unknown".to_string(), |distance| distance.to_string()));
+            }
+
+            if summary.downloaded + summary.unchanged + summary.skipped == 0 && !summary.failures.is_empty() {
+                return Err("No image could be mirrored".into());
+            }
+            Ok(())
+        }
+        Command::Derivatives { region, out, formats } => {
+            let out = out.unwrap_or_else(|| image_urls.image_dir(&region));
+            let summary = images::generate_derivatives(&out, &formats)?;
+
+            println!("Generated {}, up to date {}, failed {}", summary.generated, summary.up_to_date, summary.failures.len());
+            for (image_name, error) in &summary.failures {
+                println!("  {}: {}", image_name, error);
+            }
+            Ok(())
+        }
+        Command::Art { region, out, max_distance } => {
+            let out = out.unwrap_or_else(|| image_urls.image_dir(&region));
+            let cards = load_cards(&region, &image_urls.with_image_dir(Some(out.clone())))?;
+            let report = images::art_report(&cards, &images::Manifest::load(&out)?, max_distance);
+
+            fs::write(format!("{}/art-report.json", out), serde_json::to_string_pretty(&report)?)?;
+            println!("{} shared arts, {} suspect alternate arts, {} images not hashed",
+                report.groups.len(), report.suspect_alternate_arts.len(), report.unhashed.len());
+            for suspect in &report.suspect_alternate_arts {
+                println!("  {} {} (distance {} from {})", label(&suspect.reason), suspect.image_name, suspect.distance, suspect.regular_image_name);
+            }
+            Ok(())
+        }
+        Command::Schema { out } => {
+            schema::write_type_definitions(&out)?;
+            println!("Wrote the v{} schemas and index.d.ts to {}", schema::SCHEMA_VERSION, out);
+            Ok(())
+        }
+        Command::Deck { command: DeckCommand::Validate { file, format, region, as_of, ban_list, game_format, formats, json } } => {
+            let cards = load_cards(&region, &image_urls)?;
+            let deck = read_deck(&file, format, &cards)?;
+
+            let ban_list = match ban_list {
+                Some(path) => BanList::from_file(&path)?,
+                None => BanList::bundled(),
+            };
+            let formats = match formats {
+                Some(path) => rotation::FormatList::from_file(&path)?,
+                None => rotation::FormatList::bundled(),
+            };
+            let game_format = match game_format {
+                Some(id) => Some(formats.formats.iter().find(|format| format.id == id).ok_or_else(|| format!("Unknown format: {}", id))?),
+                None => None,
+            };
+            let as_of = as_of.unwrap_or_else(legality::today);
+
+            let violations = deck.validate(&cards, &deck::Regulation {
+                ban_list: ban_list.as_of(&as_of)?,
+                format: game_format,
+                date: as_of.clone(),
+            });
+            if json {
+                println!("{}", serde_json::to_string_pretty(&violations)?);
+            } else {
+                for violation in &violations {
+                    println!("{}", violation);
+                }
+            }
+
+            if violations.is_empty() {
+                eprintln!("{} is legal", file);
+                Ok(())
+            } else {
+                Err(format!("{} has {} rule violations", file, violations.len()).into())
+            }
+        }
+        Command::Deck { command: DeckCommand::Stats { file, format, region, json } } => {
+            let cards = load_cards(&region, &image_urls)?;
+            let stats = read_deck(&file, format, &cards)?.stats(&cards)?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+            } else {
+                print!("{}", stats);
+            }
+            Ok(())
+        }
+        Command::Deck { command: DeckCommand::Convert { file, from, to, region } } => {
+            let cards = load_cards(&region, &image_urls)?;
+            let deck = read_deck(&file, from, &cards)?;
+            println!("{}", to.write(&deck)?.trim_end());
+            Ok(())
+        }
+    }
+}
+
+fn read_deck(file: &str, format: Option<DeckFormat>, cards: &[Card]) -> Result<deck::Deck, Box<dyn std::error::Error>> {
+    let input = if file == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        fs::read_to_string(file)?
+    };
+
+    let format = format.unwrap_or_else(|| DeckFormat::detect(&input));
+    format.parse(&input, cards).map_err(|err| format!("{}: {}", file, err).into())
+}
+
+async fn scrape(export_formats: &[ExportFormat], image_urls: &ImageUrlConfig) -> Result<(), Box<dyn std::error::Error>> {
     let sources = vec![
         CardSource {
             url: "https://en.onepiece-cardgame.com/cardlist/".to_string(),
@@ -260,14 +964,33 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     fs::create_dir_all("input")?;
 
     for source in sources {
-        for color in source.colors {
-            println!("Fetching {} cards...", color);
-            
-            let form_data = [
+        let region_image_urls = image_urls.for_region(source.region)?;
+        let mut queries: Vec<(&str, Vec<(&str, &str)>)> = source.colors.iter()
+            .map(|color| (*color, vec![
                 ("freewords", ""),
                 ("series", ""),
-                ("colors[]", color),
-            ];
+                ("colors[]", *color),
+            ]))
+            .collect();
+
+        // DON!! cards have no color so they never show up in the color pages,
+        // without a DON!! series only they are left out
+        let don_series = match fetch_don_series(&client, &source.url).await {
+            Ok(don_series) => Some(don_series),
+            Err(err) => {
+                eprintln!("Skipping DON!! cards for {}: {}", source.region, err);
+                None
+            }
+        };
+        if let Some(don_series) = &don_series {
+            queries.push(("DON", vec![
+                ("freewords", ""),
+                ("series", don_series.as_str()),
+            ]));
+        }
+
+        for (label, form_data) in queries {
+            println!("Fetching {} cards...", label);
 
             let response = client
                 .post(&source.url)
@@ -286,34 +1009,63 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let html_content = response.text().await?;
             
             // Save the HTML file
-            let filename = format!("input/cardlist-{}-{}.html", color.to_lowercase(), source.region);
+            let filename = format!("input/cardlist-{}-{}.html", label.to_lowercase(), source.region);
             fs::write(&filename, &html_content)?;
             
             // Parse the cards
             let cards = parse_cards(&html_content, source.region, true)?;
             
             // Save the output
-            save_output(&cards, source.region)?;
+            save_output(&cards, source.region, &region_image_urls)?;
             
             // Be nice to the server
             thread::sleep(Duration::from_secs(2));
         }
+
+        if !export_formats.is_empty() {
+            let output_dir = format!("../json/{}", source.region);
+            let cards = load_existing_cards_from_output(&output_dir)?;
+            export::run(export_formats, &cards, &output_dir)?;
+        }
     }
 
     Ok(())
 }
 
-fn parse_cards(html_content: &str, base_image_type: &str, merge: bool) -> Result<Vec<Card>, Box<dyn std::error::Error>> {
+// Looks up the value of the DON!! entry in the card list's series filter
+async fn fetch_don_series(client: &Client, url: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let html_content = client
+        .get(url)
+        .header("User-Agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:122.0) Gecko/20100101 Firefox/122.0")
+        .send()
+        .await?
+        .text()
+        .await?;
+
+    parse_don_series(&html_content).ok_or_else(|| format!("No DON!! series found on {}", url).into())
+}
+
+fn parse_don_series(html_content: &str) -> Option<String> {
+    let document = Html::parse_document(html_content);
+    let option_selector = Selector::parse("select[name='series'] option").unwrap();
+
+    document.select(&option_selector)
+        .find(|option| option.text().collect::<String>().to_uppercase().contains("DON!!"))
+        .and_then(|option| option.value().attr("value"))
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+}
+
+fn parse_cards(html_content: &str, region: &str, merge: bool) -> Result<Vec<Card>, Box<dyn std::error::Error>> {
     let document = Html::parse_document(html_content);
     let modal_col_selector = Selector::parse("dl.modalCol").unwrap();
     
-    let base_image_type = if base_image_type == "jp" {
-        "".to_string()
+    // Image links are relative to the card list, jp images come from the Japanese site
+    let card_list_url = if region == "jp" {
+        Url::parse("https://onepiece-cardgame.com/cardlist/")?
     } else {
-        format!("{}.", base_image_type)
+        Url::parse(&format!("https://{}.onepiece-cardgame.com/cardlist/", region))?
     };
-
-    let base_image_url = format!("https://{}onepiece-cardgame.com/images/cardlist/card/", base_image_type);
     
     let mut cards = if merge {
         load_existing_cards()?
@@ -322,7 +1074,7 @@ fn parse_cards(html_content: &str, base_image_type: &str, merge: bool) -> Result
     };
     
     for element in document.select(&modal_col_selector) {
-        let card = parse_single_card(&element, &base_image_url)?;
+        let card = parse_single_card(&element, &card_list_url)?;
         
         if merge {
             if let Some(existing_idx) = find_existing_card(&cards, &card) {
@@ -338,30 +1090,31 @@ fn parse_cards(html_content: &str, base_image_type: &str, merge: bool) -> Result
     Ok(cards)
 }
 
-fn parse_single_card(element: &ElementRef, base_image_url: &str) -> Result<Card, Box<dyn std::error::Error>> {
+fn parse_single_card(element: &ElementRef, card_list_url: &Url) -> Result<Card, Box<dyn std::error::Error>> {
     let info_col_selector = Selector::parse(".infoCol > span").unwrap();
     let card_name_selector = Selector::parse(".cardName").unwrap();
     let front_col_selector = Selector::parse(".frontCol img").unwrap();
     let back_col_selector = Selector::parse(".backCol").unwrap();
     
-    // Extract basic info
-    let mut info_spans = element.select(&info_col_selector);
-    let card_number = info_spans.next()
-        .ok_or("Missing card number")?
-        .text()
-        .collect::<String>()
-        .trim()
-        .to_string();
-        
-    let rarity = parse_rarity(&info_spans.next()
-        .ok_or("Missing rarity")?
-        .text()
-        .collect::<String>())?;
-        
-    let card_type = parse_card_type(&info_spans.next()
-        .ok_or("Missing card type")?
-        .text()
-        .collect::<String>())?;
+    // Extract basic info, DON!! cards can leave out the rarity
+    let info: Vec<String> = element.select(&info_col_selector)
+        .map(|span| span.text().collect::<String>())
+        .collect();
+    let (card_number, rarity_text, card_type_text) = match info.as_slice() {
+        [card_number, rarity, card_type, ..] => (card_number, rarity.as_str(), card_type),
+        [card_number, card_type] => (card_number, "", card_type),
+        [_] => return Err("Missing card type".into()),
+        [] => return Err("Missing card number".into()),
+    };
+    let card_number = card_number.trim().to_string();
+    let card_type = parse_card_type(card_type_text)?;
+
+    // DON!! cards don't carry a real rarity on the site
+    let rarity = match parse_rarity(rarity_text) {
+        Ok(rarity) => rarity,
+        Err(_) if card_type == CardType::DON => Rarity::Unrated,
+        Err(err) => return Err(err),
+    };
     
     // Extract card name
     let card_name = decode_html_entities(&element.select(&card_name_selector)
@@ -380,19 +1133,24 @@ fn parse_single_card(element: &ElementRef, base_image_url: &str) -> Result<Card,
         .attr("data-src")
         .ok_or("Missing image src")?;
         
-    let image_name = image_src.split('/').last()
-        .ok_or("Invalid image URL")?
-        .to_string();
-        
-    let image_name = image_name.split('.').next()
-        .ok_or("Invalid image name")?
+    // Kept as linked, query string and all, the image URL templates decide image_url
+    let official_image_url = card_list_url.join(image_src.trim())?;
+
+    let file_name = official_image_url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|file_name| !file_name.is_empty())
+        .ok_or("Invalid image URL")?;
+
+    let image_name = file_name.rsplit_once('.')
+        .map_or(file_name, |(image_name, _)| image_name)
         .to_string();
 
-    let image_url = format!("{}{}.png", base_image_url, image_name);
+    let official_image_url = official_image_url.to_string();
 
-    // Extract back col info
+    // Extract back col info, DON!! cards are allowed to only be an image
     let back_col = element.select(&back_col_selector)
         .next()
+        .or(if card_type == CardType::DON { Some(*element) } else { None })
         .ok_or("Missing back column")?;
     
     let (life, cost) = parse_life_cost(&back_col)?;
@@ -402,31 +1160,47 @@ fn parse_single_card(element: &ElementRef, base_image_url: &str) -> Result<Card,
     let block_icon = parse_block_icon(&back_col)?;
     let colors = parse_colors(&back_col)?;
     let types = parse_types(&back_col)?;
-    let (effects, card_effects) = parse_effects(&back_col)?;
+    let (effects, trigger, card_effects) = parse_effects(&back_col)?;
     let card_sets = parse_card_sets(&back_col)?;
     
-    let is_alternate_art = image_url.contains(&format!("{}_", card_number)) && 
+    let is_alternate_art = image_name.contains(&format!("{}_", card_number)) && 
         !card_sets.contains("Included in");
 
     Ok(Card {
+        name_key: names::name_key(&card_name),
         card_name,
         card_number,
         rarity,
         is_alternate_art,
         card_type,
-        image_url,
+        image_url: official_image_url.clone(),
+        official_image_url,
         life,
         cost,
         attributes,
         power,
         counter,
+        effective_block_icon: block_icon.clone(),
         block_icon,
         colors,
         types,
         effects: Some(effects),
         card_effects,
+        referenced_types: Vec::new(),
+        referenced_names: Vec::new(),
+        referenced_card_numbers: Vec::new(),
+        referenced_attributes: Vec::new(),
+        counter_value: None,
+        is_counter_event: false,
+        counter_power_boost: None,
+        has_trigger: trigger.is_some(),
+        trigger_details: None,
+        trigger,
+        legality: Legality::default(),
+        legal_formats: Vec::new(),
         card_sets,
         image_name,
+        images: None,
     })
 }
 
@@ -539,11 +1313,15 @@ fn parse_types(element: &ElementRef) -> Result<Vec<String>, Box<dyn std::error::
     Ok(types)
 }
 
-fn parse_effects(element: &ElementRef) -> Result<(String, Vec<String>), Box<dyn std::error::Error>> {
+// Full effect text, the trigger on its own and the keyword effects found
+type ParsedEffects = (String, Option<String>, Vec<String>);
+
+fn parse_effects(element: &ElementRef) -> Result<ParsedEffects, Box<dyn std::error::Error>> {
     let text_selector = Selector::parse(".text").unwrap();
     let trigger_selector = Selector::parse(".trigger").unwrap();
     
     let mut effects = String::new();
+    let mut trigger = None;
     let mut card_effects = Vec::new();
     
     if let Some(text_element) = element.select(&text_selector).next() {
@@ -554,8 +1332,14 @@ fn parse_effects(element: &ElementRef) -> Result<(String, Vec<String>), Box<dyn
     }
     
     if let Some(trigger_element) = element.select(&trigger_selector).next() {
-        effects.push_str(" ");
-        effects.push_str(&trigger_element.inner_html().replace("<h3>Trigger</h3>", ""));
+        let trigger_text = trigger_element.inner_html().replace("<h3>Trigger</h3>", "");
+        effects.push(' ');
+        effects.push_str(&trigger_text);
+
+        // Keep the trigger on its own as well, without the [Trigger] label in
+        // front. Others are part of the text, e.g. "a card with a [Trigger]".
+        let trigger_text = trigger_text.trim();
+        trigger = Some(trigger_text.strip_prefix("[Trigger]").unwrap_or(trigger_text).trim().to_string());
     }
     
     // Parse card effects from the text
@@ -569,7 +1353,7 @@ fn parse_effects(element: &ElementRef) -> Result<(String, Vec<String>), Box<dyn
         card_effects.push("-".to_string());
     }
     
-    Ok((effects, card_effects))
+    Ok((effects, trigger, card_effects))
 }
 
 fn parse_card_sets(element: &ElementRef) -> Result<String, Box<dyn std::error::Error>> {
@@ -626,6 +1410,7 @@ fn parse_rarity(text: &str) -> Result<Rarity, Box<dyn std::error::Error>> {
         "SEC" => Rarity::SecretRare,
         "P" => Rarity::Promo,
         "TR" => Rarity::TreasureRare,
+        "-" => Rarity::Unrated,
         _ => return Err(format!("Unknown rarity: {}", text).into()),
     })
 }
@@ -636,7 +1421,9 @@ fn parse_card_type(text: &str) -> Result<CardType, Box<dyn std::error::Error>> {
         "STAGE" => CardType::STAGE,
         "EVENT" => CardType::EVENT,
         "CHARACTER" => CardType::CHARACTER,
-        _ => return Err(format!("Unknown card type: {}", text).into()),
+        "DON!!" | "DON" => CardType::DON,
+        "" => return Err("Missing card type".into()),
+        _ => CardType::UNKNOWN(text.trim().to_string()),
     })
 }
 
@@ -657,7 +1444,7 @@ fn load_existing_cards() -> Result<Vec<Card>, Box<dyn std::error::Error>> {
     }
 }
 
-fn save_output(new_cards: &[Card], region: &str) -> Result<(), Box<dyn std::error::Error>> {
+fn save_output(new_cards: &[Card], region: &str, image_urls: &ImageUrls) -> Result<(), Box<dyn std::error::Error>> {
     // Create output directory if it doesn't exist
     let output_dir = format!("../json/{}", region);
     fs::create_dir_all(&output_dir)?;
@@ -674,8 +1461,36 @@ fn save_output(new_cards: &[Card], region: &str) -> Result<(), Box<dyn std::erro
         }
     }
 
-    // Sort the cards
-    existing_cards.sort();
+    // Everything date dependent is as of today, and stays that way until the next scrape
+    let meta = Meta { scraped_at: legality::today() };
+    prepare_cards(&mut existing_cards, image_urls, &meta.scraped_at)?;
+
+    // Paths into the local image mirror mean nothing to whoever uses json/
+    if !image_urls.cdn_images() {
+        for card in existing_cards.iter_mut() {
+            card.images = None;
+        }
+    }
+
+    // The type specific (v2) cards data
+    let typed_cards: Vec<TypedCard> = existing_cards.iter().map(TypedCard::from).collect();
+    let filters = generate_filters(&existing_cards);
+    let search_index = search::SearchIndex::build(&existing_cards);
+
+    // Check everything against the schemas first, so a mismatch writes nothing
+    schema::validate_output("cards", &existing_cards)?;
+    schema::validate_output("cards-v2", &typed_cards)?;
+    schema::validate_output("filters", &filters)?;
+    schema::validate_output("search-index", &search_index)?;
+    schema::validate_output("meta", &meta)?;
+
+    // Keep the published schemas and TypeScript types in sync with the structs
+    schema::write_type_definitions("../json")?;
+
+    fs::write(
+        format!("{}/meta.json", output_dir),
+        serde_json::to_string_pretty(&meta)?,
+    )?;
 
     // Save full cards data
     fs::write(
@@ -683,6 +1498,11 @@ fn save_output(new_cards: &[Card], region: &str) -> Result<(), Box<dyn std::erro
         serde_json::to_string_pretty(&existing_cards)?,
     )?;
 
+    fs::write(
+        format!("{}/cards-v2.json", output_dir),
+        serde_json::to_string_pretty(&typed_cards)?,
+    )?;
+
     // // Save cards without effects
     // let cards_without_effects: Vec<_> = existing_cards.iter()
     //     .map(|card| {
@@ -697,13 +1517,66 @@ fn save_output(new_cards: &[Card], region: &str) -> Result<(), Box<dyn std::erro
     //     serde_json::to_string_pretty(&cards_without_effects)?,
     // )?;
 
-    // Generate and save filters
-    let filters = generate_filters(&existing_cards);
+    // Save filters
     fs::write(
         format!("{}/filters.json", output_dir),
         serde_json::to_string_pretty(&filters)?,
     )?;
 
+    // Save the prebuilt text search index, compact since it's only read by code
+    fs::write(
+        format!("{}/search-index.json", output_dir),
+        serde_json::to_string(&search_index)?,
+    )?;
+
+    Ok(())
+}
+
+/// Written next to cards.json by every scrape
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+struct Meta {
+    /// YYYY-MM-DD, `legality` and `legal_formats` are as of this day
+    scraped_at: String,
+}
+
+// The cards of a region, prepared as of the day they were scraped
+fn load_cards(region: &str, image_urls: &ImageUrlConfig) -> Result<Vec<Card>, Box<dyn std::error::Error>> {
+    let output_dir = format!("../json/{}", region);
+    let mut cards = load_existing_cards_from_output(&output_dir)?;
+
+    // Data scraped before the date was stored is as of today
+    let scraped_at = match fs::read_to_string(format!("{}/meta.json", output_dir)) {
+        Ok(content) => serde_json::from_str::<Meta>(&content)?.scraped_at,
+        Err(_) => legality::today(),
+    };
+    legality::check_date(&scraped_at)?;
+
+    prepare_cards(&mut cards, &image_urls.for_region(region)?, &scraped_at)?;
+    Ok(cards)
+}
+
+// Sorts the cards and fills in everything computed from the parsed fields,
+// with the ban list and rotation in effect on `as_of` (YYYY-MM-DD)
+fn prepare_cards(cards: &mut [Card], image_urls: &ImageUrls, as_of: &str) -> Result<(), Box<dyn std::error::Error>> {
+    cards.sort();
+
+    // See `deck validate --as-of` for other dates
+    let ban_list = BanList::bundled();
+    let current_ban_list = ban_list.as_of(as_of)?;
+
+    for card in cards.iter_mut() {
+        card.name_key = names::name_key(&card.card_name);
+        card.compute_counter_info();
+        card.compute_trigger_info();
+        card.legality = legality::legality(current_ban_list, &card.card_number);
+        image_urls.apply(card);
+    }
+
+    // Needs every printing of a card number, and the legality above
+    rotation::apply(cards, &rotation::FormatList::bundled(), as_of);
+
+    // Link effect text references against the merged card pool
+    resolve_references(cards);
     Ok(())
 }
 
@@ -716,20 +1589,120 @@ fn load_existing_cards_from_output(output_dir: &str) -> Result<Vec<Card>, Box<dy
     }
 }
 
-fn generate_filters(cards: &[Card]) -> serde_json::Value {
-    let mut filters = serde_json::Map::new();
-    
-    // Collect unique values for each field
-    let card_names: HashSet<_> = cards.iter().map(|c| &c.card_name).collect();
-    let card_numbers: HashSet<_> = cards.iter().map(|c| &c.card_number).collect();
-    let rarities: HashSet<_> = cards.iter().map(|c| &c.rarity).collect();
-    let card_types: HashSet<_> = cards.iter().map(|c| &c.card_type).collect();
-    let life_values: HashSet<_> = cards.iter().map(|c| &c.life).collect();
-    let cost_values: HashSet<_> = cards.iter().map(|c| &c.cost).collect();
-    let powers: HashSet<_> = cards.iter().map(|c| &c.power).collect();
-    let counters: HashSet<_> = cards.iter().map(|c| &c.counter).collect();
-    let block_icons: HashSet<_> = cards.iter().map(|c| &c.block_icon).collect();
-    let card_sets: HashSet<_> = cards.iter().map(|c| &c.card_sets).collect();
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+struct Filters {
+    colors: Vec<Color>,
+    card_names: Vec<String>,
+    card_numbers: Vec<String>,
+    rarities: Vec<Rarity>,
+    card_types: Vec<CardType>,
+    life_values: Vec<String>,
+    cost_values: Vec<String>,
+    powers: Vec<String>,
+    counters: Vec<String>,
+    block_icons: Vec<String>,
+    attributes: Vec<String>,
+    types: Vec<String>,
+    card_effects: Vec<String>,
+    card_sets: Vec<String>,
+    triggers: Vec<String>,
+    has_trigger: Vec<bool>,
+    facets: Facets,
+    ranges: Ranges,
+    breakdowns: Breakdowns,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+struct Facets {
+    rarities: Vec<FacetValue>,
+    card_types: Vec<FacetValue>,
+    colors: Vec<FacetValue>,
+    life_values: Vec<FacetValue>,
+    cost_values: Vec<FacetValue>,
+    powers: Vec<FacetValue>,
+    counters: Vec<FacetValue>,
+    block_icons: Vec<FacetValue>,
+    attributes: Vec<FacetValue>,
+    types: Vec<FacetValue>,
+    card_effects: Vec<FacetValue>,
+    card_sets: Vec<FacetValue>,
+    has_trigger: Vec<FacetValue>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+struct FacetValue {
+    value: FacetKey,
+    count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(untagged)]
+enum FacetKey {
+    Number(u32),
+    Text(String),
+    Flag(bool),
+}
+
+impl From<u32> for FacetKey {
+    fn from(value: u32) -> Self {
+        FacetKey::Number(value)
+    }
+}
+
+impl From<&String> for FacetKey {
+    fn from(value: &String) -> Self {
+        FacetKey::Text(value.clone())
+    }
+}
+
+impl From<String> for FacetKey {
+    fn from(value: String) -> Self {
+        FacetKey::Text(value)
+    }
+}
+
+impl From<bool> for FacetKey {
+    fn from(value: bool) -> Self {
+        FacetKey::Flag(value)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+struct Ranges {
+    life: Option<NumericRange>,
+    cost: Option<NumericRange>,
+    power: Option<NumericRange>,
+    counter: Option<NumericRange>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+struct NumericRange {
+    min: u32,
+    max: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
+struct Breakdowns {
+    types_per_color: BTreeMap<String, Vec<FacetValue>>,
+    sets_per_card_type: BTreeMap<String, Vec<FacetValue>>,
+}
+
+fn generate_filters(cards: &[Card]) -> Filters {
+    // Collect unique values for each field, skipping the "-" style placeholders
+    let known = |value: &&String| !is_sentinel(value);
+    let colors: HashSet<_> = cards.iter().flat_map(|c| c.colors.iter().cloned()).collect();
+    let card_names: HashSet<_> = cards.iter().map(|c| &c.card_name).filter(known).cloned().collect();
+    let card_numbers: HashSet<_> = cards.iter().map(|c| &c.card_number).filter(known).cloned().collect();
+    let rarities: HashSet<_> = cards.iter().map(|c| c.rarity.clone()).collect();
+    let card_types: HashSet<_> = cards.iter().map(|c| c.card_type.clone()).collect();
+    let life_values: HashSet<_> = cards.iter().map(|c| &c.life).filter(known).cloned().collect();
+    let cost_values: HashSet<_> = cards.iter().map(|c| &c.cost).filter(known).cloned().collect();
+    let powers: HashSet<_> = cards.iter().map(|c| &c.power).filter(known).cloned().collect();
+    let counters: HashSet<_> = cards.iter().map(|c| &c.counter).filter(known).cloned().collect();
+    let block_icons: HashSet<_> = cards.iter().map(|c| &c.block_icon).filter(known).cloned().collect();
+    let card_sets: HashSet<_> = cards.iter().map(|c| &c.card_sets).filter(known).cloned().collect();
+    let triggers: HashSet<_> = cards.iter().filter_map(|c| c.trigger.clone()).collect();
+    let has_trigger: HashSet<_> = cards.iter().map(|c| c.has_trigger).collect();
     
     // Collect all unique attributes and types across all cards
     let mut attributes = HashSet::new();
@@ -737,27 +1710,136 @@ fn generate_filters(cards: &[Card]) -> serde_json::Value {
     let mut card_effects = HashSet::new();
     
     for card in cards {
-        attributes.extend(card.attributes.iter().cloned());
-        types.extend(card.types.iter().cloned());
-        card_effects.extend(card.card_effects.iter().cloned());
+        attributes.extend(card.attributes.iter().filter(known).cloned());
+        types.extend(card.types.iter().filter(known).cloned());
+        card_effects.extend(card.card_effects.iter().filter(known).cloned());
+    }
+
+    // Value counts per facet so a search UI can render the panels directly
+    let facets = Facets {
+        rarities: facet_counts(cards.iter().map(|c| label(&c.rarity))),
+        card_types: facet_counts(cards.iter().map(|c| label(&c.card_type))),
+        colors: facet_counts(cards.iter().flat_map(|c| c.colors.iter().map(label))),
+        life_values: facet_counts(cards.iter().filter_map(|c| parse_number(&c.life))),
+        cost_values: facet_counts(cards.iter().filter_map(|c| parse_number(&c.cost))),
+        powers: facet_counts(cards.iter().filter_map(|c| parse_number(&c.power))),
+        counters: facet_counts(cards.iter().filter_map(|c| parse_number(&c.counter))),
+        block_icons: facet_counts(cards.iter().map(|c| &c.block_icon).filter(known)),
+        attributes: facet_counts(cards.iter().flat_map(|c| c.attributes.iter().filter(known))),
+        types: facet_counts(cards.iter().flat_map(|c| c.types.iter().filter(known))),
+        card_effects: facet_counts(cards.iter().flat_map(|c| c.card_effects.iter().filter(known))),
+        card_sets: facet_counts(cards.iter().map(|c| &c.card_sets).filter(known)),
+        has_trigger: facet_counts(cards.iter().map(|c| c.has_trigger)),
+    };
+
+    // Min/max for the numeric fields
+    let ranges = Ranges {
+        life: numeric_range(cards.iter().filter_map(|c| parse_number(&c.life))),
+        cost: numeric_range(cards.iter().filter_map(|c| parse_number(&c.cost))),
+        power: numeric_range(cards.iter().filter_map(|c| parse_number(&c.power))),
+        counter: numeric_range(cards.iter().filter_map(|c| parse_number(&c.counter))),
+    };
+
+    // Cross facet breakdowns
+    let mut types_per_color = BTreeMap::new();
+    for color in sorted_vec(colors.clone()) {
+        let with_color = cards.iter().filter(|c| c.colors.contains(&color));
+        types_per_color.insert(label(&color), facet_counts(with_color.flat_map(|c| c.types.iter().filter(known))));
+    }
+
+    let mut sets_per_card_type = BTreeMap::new();
+    for card_type in sorted_vec(card_types.clone()) {
+        let with_type = cards.iter().filter(|c| c.card_type == card_type);
+        sets_per_card_type.insert(label(&card_type), facet_counts(with_type.map(|c| &c.card_sets).filter(known)));
     }
     
-    // Add all collected values to filters, sorted alphabetically
-    filters.insert("card_names".to_string(), json!(sorted_vec(card_names)));
-    filters.insert("card_numbers".to_string(), json!(sorted_vec(card_numbers)));
-    filters.insert("rarities".to_string(), json!(sorted_vec(rarities)));
-    filters.insert("card_types".to_string(), json!(sorted_vec(card_types)));
-    filters.insert("life_values".to_string(), json!(sorted_vec(life_values)));
-    filters.insert("cost_values".to_string(), json!(sorted_vec(cost_values)));
-    filters.insert("powers".to_string(), json!(sorted_vec(powers)));
-    filters.insert("counters".to_string(), json!(sorted_vec(counters)));
-    filters.insert("block_icons".to_string(), json!(sorted_vec(block_icons)));
-    filters.insert("attributes".to_string(), json!(sorted_vec(attributes)));
-    filters.insert("types".to_string(), json!(sorted_vec(types)));
-    filters.insert("card_effects".to_string(), json!(sorted_vec(card_effects)));
-    filters.insert("card_sets".to_string(), json!(sorted_vec(card_sets)));
-    
-    serde_json::Value::Object(filters)
+    // Sorted alphabetically (numbers numerically)
+    Filters {
+        colors: sorted_vec(colors),
+        card_names: sorted_vec(card_names),
+        card_numbers: sorted_vec(card_numbers),
+        rarities: sorted_vec(rarities),
+        card_types: sorted_vec(card_types),
+        life_values: sorted_numeric_vec(life_values),
+        cost_values: sorted_numeric_vec(cost_values),
+        powers: sorted_numeric_vec(powers),
+        counters: sorted_numeric_vec(counters),
+        block_icons: sorted_numeric_vec(block_icons),
+        attributes: sorted_vec(attributes),
+        types: sorted_vec(types),
+        card_effects: sorted_vec(card_effects),
+        card_sets: sorted_vec(card_sets),
+        triggers: sorted_vec(triggers),
+        has_trigger: sorted_vec(has_trigger),
+        facets,
+        ranges,
+        breakdowns: Breakdowns {
+            types_per_color,
+            sets_per_card_type,
+        },
+    }
+}
+
+fn facet_counts<T: Into<FacetKey>>(values: impl IntoIterator<Item = T>) -> Vec<FacetValue> {
+    let mut counts = BTreeMap::new();
+    for value in values {
+        *counts.entry(value.into()).or_insert(0) += 1;
+    }
+
+    counts.into_iter()
+        .map(|(value, count)| FacetValue { value, count })
+        .collect()
+}
+
+fn numeric_range(values: impl Iterator<Item = u32>) -> Option<NumericRange> {
+    let values: Vec<u32> = values.collect();
+    Some(NumericRange {
+        min: *values.iter().min()?,
+        max: *values.iter().max()?,
+    })
+}
+
+// Serialized name of an enum value, e.g. "DON!!" for CardType::DON
+fn label<T: Serialize>(value: &T) -> String {
+    match json!(value) {
+        serde_json::Value::String(name) => name,
+        other => other.to_string(),
+    }
+}
+
+fn resolve_references(cards: &mut [Card]) {
+    let type_pattern = regex::Regex::new(r"\{([^}]+)\}").unwrap();
+    let attribute_pattern = regex::Regex::new(r"<([A-Za-z]+)>").unwrap();
+
+    // Only keep references that match something we actually know about
+    let mut known_types = HashSet::new();
+    let mut known_attributes = HashSet::new();
+    // Names go through their name key, so other spellings and aliases still match
+    let known_names = names::NameIndex::build(cards);
+
+    for card in cards.iter() {
+        known_types.extend(card.types.iter().cloned());
+        known_attributes.extend(card.attributes.iter().cloned());
+    }
+
+    let extract = |pattern: &regex::Regex, text: &str, known: &HashSet<String>| {
+        let found: HashSet<String> = pattern.captures_iter(text)
+            .map(|captures| captures[1].trim().to_string())
+            .filter(|value| known.contains(value))
+            .collect();
+        sorted_vec(found)
+    };
+
+    for card in cards.iter_mut() {
+        // The scraped text escapes attributes, e.g. &lt;Slash&gt;
+        let effects = decode_html_entities(card.effects.as_deref().unwrap_or_default()).into_owned();
+
+        card.referenced_types = extract(&type_pattern, &effects, &known_types);
+        let name_references = known_names.references(&effects);
+        card.referenced_names = name_references.keys().map(|name| name.to_string()).collect();
+        card.referenced_card_numbers = sorted_vec(name_references.values().flat_map(|numbers| numbers.iter().cloned()).collect());
+        card.referenced_attributes = extract(&attribute_pattern, &effects, &known_attributes);
+    }
 }
 
 fn sorted_vec<T: Ord>(set: HashSet<T>) -> Vec<T> {
@@ -766,6 +1848,48 @@ fn sorted_vec<T: Ord>(set: HashSet<T>) -> Vec<T> {
     vec
 }
 
+// Numbers first in numeric order, anything else ("X", "?") after them
+fn sorted_numeric_vec<T: AsRef<str>>(set: HashSet<T>) -> Vec<T> {
+    let mut vec: Vec<T> = set.into_iter().collect();
+    vec.sort_by(|a, b| {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        match (parse_number(a), parse_number(b)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.cmp(b),
+        }
+    });
+    vec
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Effect::ActivateMain => "[Activate: Main]",
+            Effect::Banish => "[Banish]",
+            Effect::Blocker => "[Blocker]",
+            Effect::Counter => "[Counter]",
+            Effect::DonX1 => "[DON!! x1]",
+            Effect::DonX2 => "[DON!! x2]",
+            Effect::DoubleAttack => "[Double Attack]",
+            Effect::EndOfYourTurn => "[End of Your Turn]",
+            Effect::Main => "[Main]",
+            Effect::OnBlock => "[On Block]",
+            Effect::OnKO => "[On K.O.]",
+            Effect::OnPlay => "[On Play]",
+            Effect::OnOpponentsAttack => "[On Your Opponent's Attack]",
+            Effect::OncePerTurn => "[Once Per Turn]",
+            Effect::OpponentsTurn => "[Opponent's Turn]",
+            Effect::Rush => "[Rush]",
+            Effect::Trigger => "[Trigger]",
+            Effect::WhenAttacking => "[When Attacking]",
+            Effect::YourTurn => "[Your Turn]",
+        };
+        write!(f, "{}", label)
+    }
+}
+
 impl Effect {
     fn iter() -> impl Iterator<Item = String> {
         use Effect::*;
@@ -776,28 +1900,252 @@ impl Effect {
             Trigger, WhenAttacking, YourTurn
         ].into_iter().map(|e| e.to_string())
     }
-    
-    fn to_string(&self) -> String {
-        match self {
-            Effect::ActivateMain => "[Activate: Main]".to_string(),
-            Effect::Banish => "[Banish]".to_string(),
-            Effect::Blocker => "[Blocker]".to_string(),
-            Effect::Counter => "[Counter]".to_string(),
-            Effect::DonX1 => "[DON!! x1]".to_string(),
-            Effect::DonX2 => "[DON!! x2]".to_string(),
-            Effect::DoubleAttack => "[Double Attack]".to_string(),
-            Effect::EndOfYourTurn => "[End of Your Turn]".to_string(),
-            Effect::Main => "[Main]".to_string(),
-            Effect::OnBlock => "[On Block]".to_string(),
-            Effect::OnKO => "[On K.O.]".to_string(),
-            Effect::OnPlay => "[On Play]".to_string(),
-            Effect::OnOpponentsAttack => "[On Your Opponent's Attack]".to_string(),
-            Effect::OncePerTurn => "[Once Per Turn]".to_string(),
-            Effect::OpponentsTurn => "[Opponent's Turn]".to_string(),
-            Effect::Rush => "[Rush]".to_string(),
-            Effect::Trigger => "[Trigger]".to_string(),
-            Effect::WhenAttacking => "[When Attacking]".to_string(),
-            Effect::YourTurn => "[Your Turn]".to_string(),
-        }
-    }
-}
\ No newline at end of file
+}
+
+/// A Character card for tests, with `fields` replacing the defaults
+#[cfg(test)]
+fn test_card(fields: serde_json::Value) -> Card {
+    let mut card = json!({
+        "card_name": "Test Card",
+        "card_number": "OP01-001",
+        "rarity": "C",
+        "is_alternate_art": false,
+        "card_type": "CHARACTER",
+        "image_url": "",
+        "life": "-",
+        "cost": "1",
+        "attributes": ["Strike"],
+        "power": "1000",
+        "counter": "1000",
+        "block_icon": "1",
+        "colors": ["Red"],
+        "types": ["Straw Hat Crew"],
+        "effects": null,
+        "card_effects": ["-"],
+        "card_sets": "-ROMANCE DAWN- [OP01]",
+        "image_name": "OP01-001",
+    });
+
+    if let (Some(card), Some(fields)) = (card.as_object_mut(), fields.as_object()) {
+        card.extend(fields.clone());
+    }
+
+    serde_json::from_value(card).expect("test card fields are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARD_LIST_URL: &str = "https://en.onepiece-cardgame.com/cardlist/";
+
+    fn parse_modal(html: &str) -> Result<Card, Box<dyn std::error::Error>> {
+        let document = Html::parse_fragment(html);
+        let modal = document.select(&Selector::parse("dl.modalCol").unwrap()).next().expect("fixture has a modal");
+        parse_single_card(&modal, &Url::parse(CARD_LIST_URL)?)
+    }
+
+    #[test]
+    fn parses_a_don_card_without_back_column() {
+        let card = parse_modal(r#"
+            <dl class="modalCol" id="DON-001">
+                <dt>
+                    <div class="infoCol"><span>DON-001</span> | <span>DON!!</span></div>
+                    <div class="cardName">DON!! Card</div>
+                </dt>
+                <dd>
+                    <div class="frontCol"><img class="lazy" data-src="../images/cardlist/card/DON-001.png?250101" alt="DON!! Card"></div>
+                </dd>
+            </dl>
+        "#).unwrap();
+
+        assert_eq!(card.card_number, "DON-001");
+        assert_eq!(card.card_type, CardType::DON);
+        assert_eq!(card.rarity, Rarity::Unrated);
+        assert_eq!(card.image_name, "DON-001");
+        assert_eq!(card.official_image_url, "https://en.onepiece-cardgame.com/images/cardlist/card/DON-001.png?250101");
+        assert_eq!(card.card_sets, "");
+        assert_eq!((card.cost.as_str(), card.power.as_str(), card.counter.as_str()), ("-", "-", "-"));
+        assert!(card.colors.is_empty());
+    }
+
+    #[test]
+    fn a_don_card_can_have_an_empty_rarity() {
+        let card = parse_modal(r#"
+            <dl class="modalCol" id="DON-002">
+                <dt>
+                    <div class="infoCol"><span>DON-002</span> | <span></span> | <span>DON!!</span></div>
+                    <div class="cardName">DON!! Card</div>
+                </dt>
+                <dd><div class="frontCol"><img data-src="../images/cardlist/card/DON-002.png"></div></dd>
+            </dl>
+        "#).unwrap();
+
+        assert_eq!(card.rarity, Rarity::Unrated);
+        assert!(parse_modal(r#"
+            <dl class="modalCol">
+                <dt><div class="infoCol"><span>OP01-001</span> | <span></span> | <span>LEADER</span></div><div class="cardName">Roronoa Zoro</div></dt>
+                <dd><div class="frontCol"><img data-src="../images/cardlist/card/OP01-001.png"></div><div class="backCol"></div></dd>
+            </dl>
+        "#).is_err());
+    }
+
+    #[test]
+    fn references_in_effect_text() {
+        let mut cards = vec![
+            test_card(json!({
+                "card_number": "OP01-016",
+                "card_name": "Nami",
+                "attributes": ["Special"],
+                "types": ["Straw Hat Crew"],
+            })),
+            test_card(json!({
+                "card_number": "OP01-025",
+                "card_name": "Roronoa Zoro",
+                "attributes": ["Slash"],
+                "effects": "[On Play] Up to 1 of your {Straw Hat Crew} type Characters or &lt;Slash&gt; or &lt;Wisdom&gt; attribute Characters, and [Nami] or [Luffy] or {Navy}, gain +1000 power.",
+            })),
+        ];
+
+        resolve_references(&mut cards);
+
+        let zoro = cards.iter().find(|card| card.card_number == "OP01-025").unwrap();
+        assert_eq!(zoro.referenced_types, ["Straw Hat Crew"]);
+        assert_eq!(zoro.referenced_attributes, ["Slash"]);
+        assert_eq!(zoro.referenced_names, ["Nami"]);
+        assert_eq!(zoro.referenced_card_numbers, ["OP01-016"]);
+    }
+
+    #[test]
+    fn only_the_leading_trigger_label_is_stripped() {
+        let card = parse_modal(r#"
+            <dl class="modalCol" id="OP03-072">
+                <dt>
+                    <div class="infoCol"><span>OP03-072</span> | <span>UC</span> | <span>EVENT</span></div>
+                    <div class="cardName">Gum-Gum Jet Gatling</div>
+                </dt>
+                <dd>
+                    <div class="frontCol"><img data-src="../images/cardlist/card/OP03-072.png"></div>
+                    <div class="backCol">
+                        <div class="cost"><h3>Cost</h3>1</div>
+                        <div class="color"><h3>Color</h3>Purple</div>
+                        <div class="text"><h3>Effect</h3>[Counter] Up to 1 of your Leader or Character cards gains +2000 power during this battle.</div>
+                        <div class="trigger"><h3>Trigger</h3>[Trigger] Add up to 1 card with a [Trigger] from your trash to your hand.</div>
+                        <div class="getInfo"><h3>Card Set(s)</h3>-PILLARS OF STRENGTH- [OP03]</div>
+                    </div>
+                </dd>
+            </dl>
+        "#).unwrap();
+
+        assert_eq!(card.trigger.as_deref(), Some("Add up to 1 card with a [Trigger] from your trash to your hand."));
+        assert!(card.has_trigger);
+        assert!(card.effects.as_deref().unwrap().ends_with("[Trigger] Add up to 1 card with a [Trigger] from your trash to your hand."));
+    }
+
+    #[test]
+    fn trigger_label_is_the_last_one_not_after_a_word() {
+        let effects = "[Main] Reveal up to 1 card with a [Trigger] and add it to your hand. [Trigger] Draw 1 card.";
+        assert_eq!(trigger_label_index(effects), effects.rfind("[Trigger] Draw"));
+        assert_eq!(trigger_label_index("[Main] Reveal up to 1 card with a [Trigger] and add it to your hand."), None);
+        assert_eq!(trigger_label_index("[Trigger] Play this card."), Some(0));
+        assert_eq!(trigger_label_index(""), None);
+    }
+
+    #[test]
+    fn filters_count_facets_and_ranges() {
+        let cards = [
+            test_card(json!({ "card_type": "LEADER", "life": "5", "cost": "-", "power": "5000", "counter": "-", "colors": ["Red", "Green"] })),
+            test_card(json!({ "card_number": "OP01-016", "rarity": "R", "colors": ["Red"] })),
+            test_card(json!({ "card_number": "OP01-016", "rarity": "SEC", "is_alternate_art": true, "cost": "1" })),
+            test_card(json!({ "card_number": "OP01-025", "cost": "4", "power": "6000", "counter": "-", "colors": ["Green"], "attributes": ["Slash"], "types": ["Supernovas", "Straw Hat Crew"] })),
+            test_card(json!({ "card_number": "OP01-029", "card_type": "EVENT", "cost": "2", "power": "-", "counter": "-", "attributes": ["-"], "types": ["-"] })),
+        ];
+        let filters = generate_filters(&cards);
+
+        let facets = |facet: &Vec<FacetValue>| -> Vec<(serde_json::Value, usize)> {
+            facet.iter().map(|facet| (json!(facet.value), facet.count)).collect()
+        };
+        assert_eq!(facets(&filters.facets.colors), [(json!("Green"), 2), (json!("Red"), 4)]);
+        assert_eq!(facets(&filters.facets.card_types), [(json!("CHARACTER"), 3), (json!("EVENT"), 1), (json!("LEADER"), 1)]);
+        assert_eq!(facets(&filters.facets.cost_values), [(json!(1), 2), (json!(2), 1), (json!(4), 1)]);
+        assert_eq!(facets(&filters.facets.attributes), [(json!("Slash"), 1), (json!("Strike"), 3)]);
+        assert_eq!(facets(&filters.facets.types), [(json!("Straw Hat Crew"), 4), (json!("Supernovas"), 1)]);
+        assert_eq!(facets(&filters.facets.has_trigger), [(json!(false), 5)]);
+
+        assert_eq!(json!(filters.ranges), json!({
+            "life": { "min": 5, "max": 5 },
+            "cost": { "min": 1, "max": 4 },
+            "power": { "min": 1000, "max": 6000 },
+            "counter": { "min": 1000, "max": 1000 },
+        }));
+        assert_eq!(filters.counters, ["1000"]);
+        assert_eq!(facets(&filters.breakdowns.types_per_color["Green"]), [(json!("Straw Hat Crew"), 2), (json!("Supernovas"), 1)]);
+    }
+
+    #[test]
+    fn counter_info() {
+        let counter_info = |fields| {
+            let mut card = test_card(fields);
+            card.compute_counter_info();
+            (card.counter_value, card.is_counter_event, card.counter_power_boost)
+        };
+        let event = |card_number, effects| json!({ "card_number": card_number, "card_type": "EVENT", "counter": "-", "power": "-", "effects": effects });
+
+        assert_eq!(counter_info(json!({})), (Some(1000), false, None));
+        assert_eq!(counter_info(json!({ "card_type": "LEADER", "counter": "-", "life": "5" })), (None, false, None));
+
+        // Only the first boost counts, not the conditional one or one in the trigger
+        assert_eq!(counter_info(event("OP01-029", "[Counter] Up to 1 of your Leader or Character cards gains +2000 power during this battle. Then, if you have 2 or less Life cards, that card gains an additional +2000 power. [Trigger] Up to 1 of your Leader or Character cards gains +1000 power during this turn.")), (None, true, Some(2000)));
+        assert_eq!(counter_info(event("OP01-118", "[Counter] DON!! −2 (You may return the specified number of DON!! cards from your field to your DON!! deck.): Up to 1 of your Leader or Character cards gains +2000 power during this battle. Then, draw 1 card. [Trigger] Add up to 1 DON!! card from your DON!! deck and set it as active.")), (None, true, Some(2000)));
+        assert_eq!(counter_info(event("OP01-028", "[Counter] Give up to 1 of your opponent's Leader or Character cards −2000 power during this turn. [Trigger] Activate this card's [Counter] effect.")), (None, true, None));
+        assert_eq!(counter_info(event("OP01-027", "[Main] Give up to 1 of your opponent's Characters −10000 power during this turn.")), (None, false, None));
+    }
+
+    #[test]
+    fn trigger_details() {
+        assert_eq!(TriggerDetails::parse("Play this card."), TriggerDetails {
+            cost: None,
+            effect: "Play this card.".to_string(),
+            plays_this_card: true,
+            activates: None,
+        });
+        assert_eq!(
+            TriggerDetails::parse("DON!! −1 (You may return the specified number of DON!! cards from your field to your DON!! deck.): Play this card."),
+            TriggerDetails {
+                cost: Some("DON!! −1".to_string()),
+                effect: "Play this card.".to_string(),
+                plays_this_card: true,
+                activates: None,
+            },
+        );
+        assert_eq!(TriggerDetails::parse("Activate this card's [Main] effect."), TriggerDetails {
+            cost: None,
+            effect: "Activate this card's [Main] effect.".to_string(),
+            plays_this_card: false,
+            activates: Some("[Main]".to_string()),
+        });
+        // A colon after a bracketed keyword isn't a cost
+        assert_eq!(TriggerDetails::parse("K.O. up to 1 of your opponent's Characters with a cost of 3 or less.").cost, None);
+    }
+
+    #[test]
+    fn v2_keeps_unknown_card_types() {
+        let cards = [
+            test_card(json!({ "card_type": "FIELD" })),
+            test_card(json!({ "card_type": "LEADER", "life": "5" })),
+        ];
+        let typed: Vec<TypedCard> = cards.iter().map(TypedCard::from).collect();
+
+        let json = serde_json::to_value(&typed).unwrap();
+        assert_eq!(json[0]["card_type"], "FIELD");
+        assert_eq!(json[1]["card_type"], "LEADER");
+        assert!(matches!(&typed[0].details, CardDetails::Unknown { card_type } if card_type == "FIELD"));
+
+        let back: Vec<TypedCard> = serde_json::from_value(json).unwrap();
+        assert!(matches!(&back[0].details, CardDetails::Unknown { card_type } if card_type == "FIELD"));
+        assert!(matches!(back[1].details, CardDetails::Leader { life: Some(5), .. }));
+
+        // Known types must only match their own variant
+        schema::validate_output("cards-v2", &typed).unwrap();
+    }
+}
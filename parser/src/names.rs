@@ -0,0 +1,121 @@
+use html_escape::decode_html_entities;
+use std::{collections::{BTreeMap, HashMap}, sync::OnceLock};
+
+use crate::{search::normalize, slugify, Card};
+
+// Short names and other spellings, keyed by the name as printed on the cards.
+// A name that's printed on a card itself (e.g. "Sanji") can't be an alias.
+const NAME_ALIASES: &str = include_str!("../data/name-aliases.json");
+
+fn aliases() -> &'static HashMap<String, String> {
+    static ALIASES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+    ALIASES.get_or_init(|| {
+        let table: BTreeMap<String, Vec<String>> =
+            serde_json::from_str(NAME_ALIASES).expect("data/name-aliases.json is valid");
+
+        let mut aliases = HashMap::new();
+        for (name, alternatives) in table {
+            let key = normalized_key(&name);
+            for alternative in alternatives {
+                aliases.insert(normalized_key(&alternative), key.clone());
+            }
+        }
+        aliases
+    })
+}
+
+fn normalized_key(name: &str) -> String {
+    slugify(&normalize(&decode_html_entities(name)))
+}
+
+/// Canonical identity for a card name, so `Monkey.D.Luffy`, `Monkey D. Luffy`
+/// and `Luffy` all become `monkey-d-luffy`
+pub fn name_key(name: &str) -> String {
+    let key = normalized_key(name);
+    aliases().get(&key).cloned().unwrap_or(key)
+}
+
+/// Card numbers by name key, for matching names typed by people or
+/// referenced in effect text against the cards that carry them
+#[derive(Debug, Default)]
+pub struct NameIndex {
+    card_numbers: BTreeMap<String, Vec<String>>,
+    names: BTreeMap<String, String>,
+}
+
+impl NameIndex {
+    pub fn build(cards: &[Card]) -> NameIndex {
+        let mut index = NameIndex::default();
+
+        for card in cards {
+            let key = name_key(&card.card_name);
+            let card_numbers = index.card_numbers.entry(key.clone()).or_default();
+            if !card_numbers.contains(&card.card_number) {
+                card_numbers.push(card.card_number.clone());
+            }
+            index.names.entry(key).or_insert_with(|| card.card_name.clone());
+        }
+
+        index
+    }
+
+    /// Every card number printed with this name, under any spelling or alias
+    pub fn resolve(&self, name: &str) -> &[String] {
+        self.card_numbers.get(&name_key(name)).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// The name as printed on the cards, for any spelling or alias of it
+    pub fn printed_name(&self, name: &str) -> Option<&str> {
+        self.names.get(&name_key(name)).map(String::as_str)
+    }
+
+    /// The `[Name]` references in effect text that name a known card, keyed
+    /// by the printed name with every card number carrying it
+    pub fn references(&self, text: &str) -> BTreeMap<&str, &[String]> {
+        let name_pattern = regex::Regex::new(r"\[([^\]]+)\]").unwrap();
+
+        name_pattern.captures_iter(text)
+            .filter_map(|captures| {
+                let name = self.printed_name(&captures[1])?;
+                Some((name, self.resolve(name)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_card;
+    use serde_json::json;
+
+    #[test]
+    fn spellings_share_a_key() {
+        for name in ["Monkey.D.Luffy", "Monkey D. Luffy", "monkey d luffy", "Luffy", "Straw Hat Luffy", "ＬＵＦＦＹ"] {
+            assert_eq!(name_key(name), "monkey-d-luffy", "{}", name);
+        }
+        assert_eq!(name_key("Eustass&quot;Captain&quot;Kid"), name_key("Kid"));
+        assert_eq!(name_key("Sanji"), "sanji");
+    }
+
+    #[test]
+    fn resolves_names_and_references() {
+        let index = NameIndex::build(&[
+            test_card(json!({ "card_number": "OP01-024", "card_name": "Monkey.D.Luffy" })),
+            test_card(json!({ "card_number": "ST01-001", "card_name": "Monkey.D.Luffy" })),
+            test_card(json!({ "card_number": "ST01-001", "card_name": "Monkey.D.Luffy", "is_alternate_art": true })),
+            test_card(json!({ "card_number": "OP01-016", "card_name": "Nami" })),
+        ]);
+
+        assert_eq!(index.resolve("Luffy"), ["OP01-024", "ST01-001"]);
+        assert_eq!(index.printed_name("monkey d luffy"), Some("Monkey.D.Luffy"));
+        assert!(index.resolve("Zoro").is_empty());
+
+        let references = index.references("[On Play] If your Leader is [Luffy], add up to 1 [Nami] or [Zoro] from your trash to your hand.");
+        assert_eq!(references, BTreeMap::from([
+            ("Monkey.D.Luffy", &["OP01-024".to_string(), "ST01-001".to_string()][..]),
+            ("Nami", &["OP01-016".to_string()][..]),
+        ]));
+    }
+}
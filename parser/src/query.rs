@@ -0,0 +1,479 @@
+use std::fmt;
+
+use crate::{label, legality::LegalityStatus, names::name_key, parse_number, set_key, Card, CardType};
+
+/// Parsed search query, e.g. `c:red t:character cost<=4 trait:"Straw Hat Crew"`.
+/// Terms next to each other are ANDed, `or` between them ORs, a leading `-`
+/// negates and parentheses group.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub field: Field,
+    pub op: Op,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Color,
+    CardType,
+    Cost,
+    Power,
+    Counter,
+    Life,
+    Trait,
+    Attribute,
+    Keyword,
+    Text,
+    Set,
+    Rarity,
+    BlockIcon,
+    Format,
+    Is,
+}
+
+/// Everything `is:` takes
+const IS_VALUES: &[&str] = &[
+    "trigger", "counter", "alt", "alternate", "leader", "character", "event", "stage", "don",
+    "legal", "restricted", "banned",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// `:` - contains for text fields, equals for numbers
+    Matches,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Field> {
+        Some(match name.to_lowercase().as_str() {
+            "n" | "name" => Field::Name,
+            "c" | "color" | "colour" => Field::Color,
+            "t" | "type" => Field::CardType,
+            "cost" => Field::Cost,
+            "p" | "pow" | "power" => Field::Power,
+            "counter" => Field::Counter,
+            "life" => Field::Life,
+            "tr" | "trait" => Field::Trait,
+            "a" | "attr" | "attribute" => Field::Attribute,
+            "kw" | "keyword" => Field::Keyword,
+            "o" | "text" | "effect" => Field::Text,
+            "s" | "e" | "set" => Field::Set,
+            "r" | "rarity" => Field::Rarity,
+            "b" | "block" => Field::BlockIcon,
+            "f" | "format" | "legal" => Field::Format,
+            "is" | "has" => Field::Is,
+            _ => return None,
+        })
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Field::Cost | Field::Power | Field::Counter | Field::Life)
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Op::Matches => ":",
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Not,
+    Or,
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, Box<dyn std::error::Error>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                // A word runs until whitespace or a paren, quotes can wrap either
+                let mut word = String::new();
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if c == '"' {
+                        quoted = !quoted;
+                    } else if !quoted && (c.is_whitespace() || c == '(' || c == ')') {
+                        break;
+                    } else {
+                        word.push(c);
+                    }
+                    chars.next();
+                }
+
+                if quoted {
+                    return Err(format!("Unclosed quote in: {}", input).into());
+                }
+
+                if word.eq_ignore_ascii_case("or") {
+                    tokens.push(Token::Or);
+                } else {
+                    tokens.push(Token::Word(word));
+                }
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Query, Box<dyn std::error::Error>> {
+        let mut alternatives = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            alternatives.push(self.parse_and()?);
+        }
+
+        Ok(if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            Query::Or(alternatives)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Query, Box<dyn std::error::Error>> {
+        let mut terms = Vec::new();
+        while let Some(token) = self.peek() {
+            if matches!(token, Token::Or | Token::RParen) {
+                break;
+            }
+            terms.push(self.parse_unary()?);
+        }
+
+        match terms.len() {
+            0 => Err("Expected a search term".into()),
+            1 => Ok(terms.remove(0)),
+            _ => Ok(Query::And(terms)),
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, Box<dyn std::error::Error>> {
+        match self.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Some(Token::LParen) => {
+                let inner = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => Err("Missing closing parenthesis".into()),
+                }
+            }
+            Some(Token::Word(word)) => Ok(Query::Term(parse_term(&word)?)),
+            Some(token) => Err(format!("Unexpected {:?}", token).into()),
+            None => Err("Unexpected end of query".into()),
+        }
+    }
+}
+
+fn parse_term(word: &str) -> Result<Term, Box<dyn std::error::Error>> {
+    let term_pattern = regex::Regex::new(r"^([A-Za-z]+)(<=|>=|!=|:|=|<|>)(.*)$").unwrap();
+
+    let Some(captures) = term_pattern.captures(word) else {
+        // Bare words search the name
+        return Ok(Term { field: Field::Name, op: Op::Matches, value: word.to_string() });
+    };
+
+    let field = Field::parse(&captures[1]).ok_or_else(|| format!("Unknown field: {}", &captures[1]))?;
+    let op = match &captures[2] {
+        ":" => Op::Matches,
+        "=" => Op::Eq,
+        "!=" => Op::Ne,
+        "<" => Op::Lt,
+        "<=" => Op::Le,
+        ">" => Op::Gt,
+        _ => Op::Ge,
+    };
+    let value = captures[3].to_string();
+
+    if value.is_empty() {
+        return Err(format!("Missing value for {}", &captures[1]).into());
+    }
+
+    if field.is_numeric() && value.parse::<u32>().is_err() {
+        return Err(format!("{} needs a number, got {}", &captures[1], value).into());
+    }
+
+    if !field.is_numeric() && matches!(op, Op::Lt | Op::Le | Op::Gt | Op::Ge) {
+        return Err(format!("{} can't be compared with {}", &captures[1], op).into());
+    }
+
+    if field == Field::Is && !IS_VALUES.contains(&value.to_lowercase().as_str()) {
+        return Err(format!("Unknown {} value: {}, expected one of {}", &captures[1], value, IS_VALUES.join(", ")).into());
+    }
+
+    Ok(Term { field, op, value })
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, Box<dyn std::error::Error>> {
+        let mut parser = Parser { tokens: tokenize(input)?, position: 0 };
+        let query = parser.parse_or()?;
+
+        match parser.peek() {
+            None => Ok(query),
+            Some(token) => Err(format!("Unexpected {:?}", token).into()),
+        }
+    }
+
+    pub fn matches(&self, card: &Card) -> bool {
+        match self {
+            Query::And(terms) => terms.iter().all(|term| term.matches(card)),
+            Query::Or(terms) => terms.iter().any(|term| term.matches(card)),
+            Query::Not(inner) => !inner.matches(card),
+            Query::Term(term) => term.matches(card),
+        }
+    }
+}
+
+impl Term {
+    pub fn matches(&self, card: &Card) -> bool {
+        let wanted = self.value.to_lowercase();
+
+        match self.field {
+            Field::Cost => self.compare_number(parse_number(&card.cost)),
+            Field::Power => self.compare_number(parse_number(&card.power)),
+            Field::Counter => self.compare_number(parse_number(&card.counter)),
+            Field::Life => self.compare_number(parse_number(&card.life)),
+            Field::Color if self.op == Op::Eq => {
+                // c=red is exactly red, c:red is red and anything else
+                let mut colors: Vec<String> = card.colors.iter().map(|color| label(color).to_lowercase()).collect();
+                let mut wanted: Vec<String> = wanted.split(['/', ',']).map(str::to_string).collect();
+                colors.sort();
+                wanted.sort();
+                colors == wanted
+            }
+            Field::Color => self.compare_any(card.colors.iter().map(label)),
+            Field::CardType => self.compare_text(&label(&card.card_type)),
+            // n= compares name keys, so other spellings and aliases match too
+            Field::Name if matches!(self.op, Op::Eq | Op::Ne) => self.negate(card.name_key == name_key(&self.value)),
+            Field::Name => self.compare_text(&card.card_name),
+            Field::Trait => self.compare_any(card.types.iter().cloned()),
+            Field::Attribute => self.compare_any(card.attributes.iter().cloned()),
+            Field::Keyword => self.compare_any(card.card_effects.iter().map(|effect| effect.trim_matches(['[', ']']).to_string())),
+            Field::Text => self.compare_any([card.effects.clone(), card.trigger.clone()].into_iter().flatten()),
+            Field::Set => {
+                let normalize = |value: &str| value.replace('-', "").to_lowercase();
+                let key = set_key(&card.card_sets).unwrap_or_default();
+                self.negate(normalize(&key) == normalize(&wanted))
+            }
+            Field::Rarity => self.negate(label(&card.rarity).eq_ignore_ascii_case(&wanted)),
+            Field::BlockIcon => self.negate(card.block_icon.eq_ignore_ascii_case(&wanted)),
+            Field::Format => self.negate(card.legal_formats.iter().any(|format| format.eq_ignore_ascii_case(&wanted))),
+            Field::Is => self.negate(match wanted.as_str() {
+                "trigger" => card.has_trigger,
+                "counter" => card.is_counter_event,
+                "alt" | "alternate" => card.is_alternate_art,
+                "leader" => card.card_type == CardType::LEADER,
+                "character" => card.card_type == CardType::CHARACTER,
+                "event" => card.card_type == CardType::EVENT,
+                "stage" => card.card_type == CardType::STAGE,
+                "don" => card.card_type == CardType::DON,
+                "legal" => card.legality.status == LegalityStatus::Legal,
+                "restricted" => card.legality.status == LegalityStatus::Restricted,
+                "banned" => card.legality.status == LegalityStatus::Banned,
+                _ => false,
+            }),
+        }
+    }
+
+    fn negate(&self, matched: bool) -> bool {
+        if self.op == Op::Ne {
+            !matched
+        } else {
+            matched
+        }
+    }
+
+    fn compare_number(&self, value: Option<u32>) -> bool {
+        let Some(value) = value else {
+            return false;
+        };
+        let wanted: u32 = self.value.parse().unwrap_or_default();
+
+        match self.op {
+            Op::Matches | Op::Eq => value == wanted,
+            Op::Ne => value != wanted,
+            Op::Lt => value < wanted,
+            Op::Le => value <= wanted,
+            Op::Gt => value > wanted,
+            Op::Ge => value >= wanted,
+        }
+    }
+
+    // `:` is a case insensitive substring match, `=` an exact one
+    fn compare_text(&self, value: &str) -> bool {
+        let value = value.to_lowercase();
+        let wanted = self.value.to_lowercase();
+
+        self.negate(match self.op {
+            Op::Matches => value.contains(&wanted),
+            _ => value == wanted,
+        })
+    }
+
+    fn compare_any(&self, values: impl IntoIterator<Item = String>) -> bool {
+        let wanted = self.value.to_lowercase();
+        let matched = values.into_iter().any(|value| {
+            let value = value.to_lowercase();
+            match self.op {
+                Op::Matches => value.contains(&wanted),
+                _ => value == wanted,
+            }
+        });
+
+        self.negate(matched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(field: Field, op: Op, value: &str) -> Query {
+        Query::Term(Term { field, op, value: value.to_string() })
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            Query::parse("c:red cost<=4 or c:blue").unwrap(),
+            Query::Or(vec![
+                Query::And(vec![term(Field::Color, Op::Matches, "red"), term(Field::Cost, Op::Le, "4")]),
+                term(Field::Color, Op::Matches, "blue"),
+            ]),
+        );
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert_eq!(
+            Query::parse("c:red (t:leader OR t:stage)").unwrap(),
+            Query::And(vec![
+                term(Field::Color, Op::Matches, "red"),
+                Query::Or(vec![term(Field::CardType, Op::Matches, "leader"), term(Field::CardType, Op::Matches, "stage")]),
+            ]),
+        );
+    }
+
+    #[test]
+    fn negation_applies_to_the_next_term_or_group() {
+        assert_eq!(
+            Query::parse("-kw:blocker c:red").unwrap(),
+            Query::And(vec![
+                Query::Not(Box::new(term(Field::Keyword, Op::Matches, "blocker"))),
+                term(Field::Color, Op::Matches, "red"),
+            ]),
+        );
+        assert_eq!(
+            Query::parse("-(c:red or c:blue)").unwrap(),
+            Query::Not(Box::new(Query::Or(vec![
+                term(Field::Color, Op::Matches, "red"),
+                term(Field::Color, Op::Matches, "blue"),
+            ]))),
+        );
+    }
+
+    #[test]
+    fn numeric_operators() {
+        let ops = [("cost:3", Op::Matches), ("cost=3", Op::Eq), ("cost!=3", Op::Ne), ("cost<3", Op::Lt), ("cost<=3", Op::Le), ("cost>3", Op::Gt), ("cost>=3", Op::Ge)];
+        for (query, op) in ops {
+            assert_eq!(Query::parse(query).unwrap(), term(Field::Cost, op, "3"), "{}", query);
+        }
+
+        let matching = |query: &str| (0..=6).filter(|cost| match Query::parse(query).unwrap() {
+            Query::Term(term) => term.compare_number(Some(*cost)),
+            _ => unreachable!(),
+        }).collect::<Vec<_>>();
+        assert_eq!(matching("cost:3"), vec![3]);
+        assert_eq!(matching("cost!=3"), vec![0, 1, 2, 4, 5, 6]);
+        assert_eq!(matching("cost<3"), vec![0, 1, 2]);
+        assert_eq!(matching("cost<=3"), vec![0, 1, 2, 3]);
+        assert_eq!(matching("cost>3"), vec![4, 5, 6]);
+        assert_eq!(matching("cost>=3"), vec![3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn quotes_keep_spaces_and_parentheses() {
+        assert_eq!(Query::parse(r#"trait:"Straw Hat Crew""#).unwrap(), term(Field::Trait, Op::Matches, "Straw Hat Crew"));
+        assert_eq!(Query::parse(r#"o:"(this turn)" c:red"#).unwrap(), Query::And(vec![
+            term(Field::Text, Op::Matches, "(this turn)"),
+            term(Field::Color, Op::Matches, "red"),
+        ]));
+        assert_eq!(Query::parse(r#""Monkey.D.Luffy""#).unwrap(), term(Field::Name, Op::Matches, "Monkey.D.Luffy"));
+        assert!(Query::parse(r#"trait:"Straw Hat"#).is_err());
+    }
+
+    #[test]
+    fn invalid_queries() {
+        for query in ["", "c:red or", "(c:red", "c:red)", "cost<=many", "c>red", "nope:red", "c:"] {
+            assert!(Query::parse(query).is_err(), "{}", query);
+        }
+    }
+
+    #[test]
+    fn unknown_is_values_list_the_accepted_ones() {
+        assert!(Query::parse("is:Trigger").is_ok());
+        let error = Query::parse("is:shiny").unwrap_err().to_string();
+        assert!(error.contains("shiny") && error.contains("trigger, counter"), "{}", error);
+    }
+}
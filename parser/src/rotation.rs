@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs};
+
+use crate::{legality::{check_date, LegalityStatus}, parse_number, Card};
+
+// Formats and their block rotation schedules
+const FORMATS: &str = include_str!("../data/formats.json");
+
+/// Bump when the layout of the formats file changes
+pub const FORMATS_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatList {
+    pub version: u32,
+    pub formats: Vec<Format>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Format {
+    pub id: String,
+    pub name: String,
+    /// Formats without rotations, like Extra Regulation, allow every block
+    #[serde(default)]
+    pub rotations: Vec<Rotation>,
+}
+
+/// Blocks leaving the format on `effective_date`, earlier rotations still apply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rotation {
+    /// YYYY-MM-DD
+    pub effective_date: String,
+    pub rotated_blocks: Vec<String>,
+}
+
+impl FormatList {
+    /// The formats bundled from `data/formats.json`
+    pub fn bundled() -> FormatList {
+        FormatList::parse(FORMATS).expect("data/formats.json is valid")
+    }
+
+    pub fn from_file(path: &str) -> Result<FormatList, Box<dyn std::error::Error>> {
+        FormatList::parse(&fs::read_to_string(path)?).map_err(|err| format!("{}: {}", path, err).into())
+    }
+
+    fn parse(content: &str) -> Result<FormatList, Box<dyn std::error::Error>> {
+        let formats: FormatList = serde_json::from_str(content)?;
+
+        if formats.version != FORMATS_VERSION {
+            return Err(format!("Unsupported formats version {}, expected {}", formats.version, FORMATS_VERSION).into());
+        }
+
+        for rotation in formats.formats.iter().flat_map(|format| &format.rotations) {
+            check_date(&rotation.effective_date)?;
+        }
+
+        Ok(formats)
+    }
+
+    /// Ids of the formats a card from `block_icon` can be played in on `date`
+    pub fn legal_formats(&self, block_icon: &str, date: &str) -> Vec<String> {
+        self.formats.iter()
+            .filter(|format| format.allows(block_icon, date))
+            .map(|format| format.id.clone())
+            .collect()
+    }
+}
+
+impl Format {
+    pub fn allows(&self, block_icon: &str, date: &str) -> bool {
+        !self.rotations.iter()
+            .filter(|rotation| rotation.effective_date.as_str() <= date)
+            .any(|rotation| rotation.rotated_blocks.iter().any(|block| block == block_icon))
+    }
+}
+
+/// The block icon that decides rotation for each card number: reprints
+/// move a card into a newer block, so the highest numbered block across its
+/// printings wins. Cards only printed without a numbered block keep their icon.
+pub fn effective_block_icons(cards: &[Card]) -> BTreeMap<String, String> {
+    let mut blocks: BTreeMap<String, String> = BTreeMap::new();
+
+    for card in cards {
+        let newer = blocks.get(&card.card_number).is_none_or(|current| {
+            match (parse_number(&card.block_icon), parse_number(current)) {
+                (Some(block), Some(current)) => block > current,
+                (Some(_), None) => true,
+                _ => false,
+            }
+        });
+
+        if newer {
+            blocks.insert(card.card_number.clone(), card.block_icon.clone());
+        }
+    }
+
+    blocks
+}
+
+/// Fills in `effective_block_icon` and `legal_formats` for every printing,
+/// as of `date`. Banned cards aren't legal in any format.
+pub fn apply(cards: &mut [Card], formats: &FormatList, date: &str) {
+    let blocks = effective_block_icons(cards);
+
+    for card in cards.iter_mut() {
+        let block_icon = blocks.get(&card.card_number).cloned().unwrap_or_else(|| card.block_icon.clone());
+
+        card.legal_formats = if card.legality.status == LegalityStatus::Banned {
+            Vec::new()
+        } else {
+            formats.legal_formats(&block_icon, date)
+        };
+        card.effective_block_icon = block_icon;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn formats() -> FormatList {
+        FormatList::parse(r#"{
+            "version": 1,
+            "formats": [
+                { "id": "standard", "name": "Standard", "rotations": [{ "effective_date": "2026-04-01", "rotated_blocks": ["1"] }] },
+                { "id": "extra", "name": "Extra Regulation", "rotations": [] }
+            ]
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn a_reprint_moves_a_card_to_the_newest_block() {
+        let cards = [
+            crate::test_card(json!({ "card_number": "OP01-016", "block_icon": "1" })),
+            crate::test_card(json!({ "card_number": "OP01-016", "block_icon": "3", "image_name": "OP01-016_r1" })),
+            crate::test_card(json!({ "card_number": "OP01-016", "block_icon": "2", "image_name": "OP01-016_r2" })),
+            crate::test_card(json!({ "card_number": "OP01-025", "block_icon": "1" })),
+            crate::test_card(json!({ "card_number": "P-001", "block_icon": "" })),
+        ];
+
+        let blocks = effective_block_icons(&cards);
+
+        assert_eq!(blocks["OP01-016"], "3");
+        assert_eq!(blocks["OP01-025"], "1");
+        assert_eq!(blocks["P-001"], "");
+    }
+
+    #[test]
+    fn rotation_starts_on_its_effective_date() {
+        let standard = &formats().formats[0];
+
+        assert!(standard.allows("1", "2026-03-31"));
+        assert!(!standard.allows("1", "2026-04-01"));
+        assert!(standard.allows("2", "2026-04-01"));
+        assert_eq!(formats().legal_formats("1", "2026-04-01"), ["extra"]);
+    }
+
+    #[test]
+    fn apply_uses_the_reprint_block_and_skips_banned_cards() {
+        let mut cards = [
+            crate::test_card(json!({ "card_number": "OP01-016", "block_icon": "1" })),
+            crate::test_card(json!({ "card_number": "OP01-016", "block_icon": "2", "image_name": "OP01-016_r1" })),
+            crate::test_card(json!({ "card_number": "OP01-025", "block_icon": "1" })),
+            crate::test_card(json!({ "card_number": "OP02-024", "block_icon": "1" })),
+        ];
+        cards[3].legality.status = LegalityStatus::Banned;
+
+        apply(&mut cards, &formats(), "2026-04-01");
+
+        assert_eq!(cards[0].effective_block_icon, "2");
+        assert_eq!(cards[0].legal_formats, ["standard", "extra"]);
+        assert_eq!(cards[2].legal_formats, ["extra"]);
+        assert!(cards[3].legal_formats.is_empty());
+    }
+}
@@ -0,0 +1,302 @@
+use schemars::{gen::SchemaSettings, JsonSchema};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::{fs, collections::BTreeMap};
+
+use crate::{search::SearchIndex, Card, Filters, Meta, TypedCard};
+
+/// Bump whenever a change to the output would break existing consumers,
+/// older versions stay around under `schema/v{n}/`
+pub const SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA_BASE_URL: &str = "https://unpkg.com/one-piece-card-game-json/schema";
+
+// Fields left out of the JSON when None, typed `name?: T` instead of `name: T | null`
+const OMITTED_WHEN_NONE: [&str; 1] = ["images"];
+
+const MODULE_DECLARATION: &str = "declare module 'one-piece-card-game-json' {
+  export const all: Card[];
+  export const en: {
+    cards: Card[];
+    filters: Filters;
+  };
+  export const jp: {
+    cards: Card[];
+    filters: Filters;
+  };
+}
+";
+
+// One schema per file written by `save_output`, new output files get added here
+fn file_schemas() -> Vec<(&'static str, Value)> {
+    vec![
+        ("cards", file_schema::<Vec<Card>>("cards")),
+        ("cards-v2", file_schema::<Vec<TypedCard>>("cards-v2")),
+        ("filters", file_schema::<Filters>("filters")),
+        ("search-index", file_schema::<SearchIndex>("search-index")),
+        ("meta", file_schema::<Meta>("meta")),
+    ]
+}
+
+fn file_schema<T: JsonSchema>(file: &str) -> Value {
+    let mut generator = SchemaSettings::draft07().into_generator();
+    let root = serde_json::to_value(generator.subschema_for::<T>()).expect("schemas always serialize");
+
+    let mut document = json!({
+        "$schema": "http://json-schema.org/draft-07/schema#",
+        "$id": format!("{}/v{}/{}.schema.json", SCHEMA_BASE_URL, SCHEMA_VERSION, file),
+        "title": file,
+        "version": SCHEMA_VERSION,
+    });
+
+    if let (Some(document), Value::Object(root)) = (document.as_object_mut(), root) {
+        document.extend(root);
+        document.insert("definitions".to_string(), json!(generator.definitions()));
+    }
+
+    document
+}
+
+/// Writes the versioned JSON Schemas and `index.d.ts` for the npm package so
+/// they can't drift from what `save_output` actually writes, the `schema`
+/// command runs it without scraping
+pub fn write_type_definitions(json_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    fs::create_dir_all(format!("{}/schema/v{}", json_dir, SCHEMA_VERSION))?;
+
+    for (path, content) in type_definition_files()? {
+        fs::write(format!("{}/{}", json_dir, path), content)?;
+    }
+
+    Ok(())
+}
+
+// Every generated file with its path relative to the json directory
+fn type_definition_files() -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let schema_dir = format!("schema/v{}", SCHEMA_VERSION);
+
+    let mut definitions = BTreeMap::new();
+    let mut files = Vec::new();
+    let mut output = Vec::new();
+
+    for (name, schema) in file_schemas() {
+        let file_name = format!("{}.schema.json", name);
+        output.push((format!("{}/{}", schema_dir, file_name), serde_json::to_string_pretty(&schema)?));
+
+        if let Some(nested) = schema["definitions"].as_object() {
+            for (key, value) in nested {
+                definitions.insert(key.clone(), value.clone());
+            }
+        }
+
+        files.push(json!({ "file": format!("{}.json", name), "schema": file_name }));
+    }
+
+    output.push((
+        format!("{}/index.json", schema_dir),
+        serde_json::to_string_pretty(&json!({ "version": SCHEMA_VERSION, "files": files }))?,
+    ));
+    output.push(("index.d.ts".to_string(), typescript_declarations(&definitions)));
+
+    Ok(output)
+}
+
+/// Checks a generated output file against its schema before it gets written
+pub fn validate_output<T: Serialize>(name: &str, data: &T) -> Result<(), Box<dyn std::error::Error>> {
+    let (_, schema) = file_schemas()
+        .into_iter()
+        .find(|(file, _)| *file == name)
+        .ok_or_else(|| format!("No schema for {}", name))?;
+
+    let validator = jsonschema::validator_for(&schema)
+        .map_err(|err| format!("Invalid {} schema: {}", name, err))?;
+    let instance = serde_json::to_value(data)?;
+
+    let errors: Vec<String> = validator.iter_errors(&instance)
+        .take(10)
+        .map(|err| format!("{} at {}", err, err.instance_path))
+        .collect();
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{}.json does not match its schema:\n{}", name, errors.join("\n")).into())
+    }
+}
+
+fn typescript_declarations(definitions: &BTreeMap<String, Value>) -> String {
+    let mut output = String::from("// Generated from the parser's Rust types, do not edit by hand.\n\n");
+
+    for (name, schema) in definitions {
+        output.push_str(&doc_comment(schema, ""));
+
+        if is_plain_object(schema) {
+            output.push_str(&format!("export interface {} {}\n\n", name, object_type(schema, "")));
+        } else {
+            output.push_str(&format!("export type {} = {};\n\n", name, typescript_type(schema, "")));
+        }
+    }
+
+    output.push_str(MODULE_DECLARATION);
+    output
+}
+
+fn is_plain_object(schema: &Value) -> bool {
+    schema["properties"].is_object() && schema["oneOf"].is_null() && schema["anyOf"].is_null()
+}
+
+fn doc_comment(schema: &Value, indent: &str) -> String {
+    match schema["description"].as_str() {
+        Some(description) => format!("{}/** {} */\n", indent, description.replace('\n', " ")),
+        None => String::new(),
+    }
+}
+
+fn typescript_type(schema: &Value, indent: &str) -> String {
+    if let Some(reference) = schema["$ref"].as_str() {
+        return reference.rsplit('/').next().unwrap_or(reference).to_string();
+    }
+
+    if let Some(values) = schema["enum"].as_array() {
+        return values.iter().map(Value::to_string).collect::<Vec<_>>().join(" | ");
+    }
+
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+
+    if let Some([single]) = schema["allOf"].as_array().map(Vec::as_slice) {
+        return typescript_type(single, indent);
+    }
+
+    let variants = schema["oneOf"].as_array().or(schema["anyOf"].as_array());
+
+    if schema["properties"].is_object() {
+        let object = object_type(schema, indent);
+        return match variants {
+            Some(variants) => format!("{} & ({})", object, union(variants, indent)),
+            None => object,
+        };
+    }
+
+    if let Some(variants) = variants {
+        return union(variants, indent);
+    }
+
+    match &schema["type"] {
+        Value::Array(types) => types.iter()
+            .map(|instance_type| {
+                let mut single = schema.clone();
+                single["type"] = instance_type.clone();
+                typescript_type(&single, indent)
+            })
+            .collect::<Vec<_>>()
+            .join(" | "),
+        Value::String(instance_type) => match instance_type.as_str() {
+            "string" => "string".to_string(),
+            "integer" | "number" => "number".to_string(),
+            "boolean" => "boolean".to_string(),
+            "null" => "null".to_string(),
+            "array" if schema["items"].is_array() => {
+                let items = schema["items"].as_array().map(|items| union(items, indent).replace(" | ", ", "));
+                format!("[{}]", items.unwrap_or_default())
+            }
+            "array" => {
+                let items = typescript_type(&schema["items"], indent);
+                if items.contains(' ') {
+                    format!("({})[]", items)
+                } else {
+                    format!("{}[]", items)
+                }
+            }
+            "object" => match &schema["additionalProperties"] {
+                Value::Object(_) => format!("Record<string, {}>", typescript_type(&schema["additionalProperties"], indent)),
+                _ => "Record<string, unknown>".to_string(),
+            },
+            _ => "unknown".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
+
+fn union(variants: &[Value], indent: &str) -> String {
+    variants.iter()
+        .map(|variant| typescript_type(variant, indent))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn object_type(schema: &Value, indent: &str) -> String {
+    let empty = Map::new();
+    let properties = schema["properties"].as_object().unwrap_or(&empty);
+    let inner = format!("{}  ", indent);
+
+    // Every field is always serialized (None as null), except the skip_serializing_if
+    // ones in OMITTED_WHEN_NONE, which schemars can't tell apart from the rest
+    let mut output = String::from("{\n");
+    for (name, property) in properties {
+        output.push_str(&doc_comment(property, &inner));
+        match OMITTED_WHEN_NONE.contains(&name.as_str()) {
+            true => output.push_str(&format!("{}{}?: {};\n", inner, name, typescript_type(&non_null(property), &inner))),
+            false => output.push_str(&format!("{}{}: {};\n", inner, name, typescript_type(property, &inner))),
+        }
+    }
+    output.push_str(indent);
+    output.push('}');
+    output
+}
+
+// An `Option` left out when None is never null, so drop that variant
+fn non_null(property: &Value) -> Value {
+    let mut property = property.clone();
+    if let Some(variants) = property["anyOf"].as_array_mut() {
+        variants.retain(|variant| variant["type"] != "null");
+        if variants.len() == 1 {
+            let variant = variants.remove(0);
+            property.as_object_mut().unwrap().remove("anyOf");
+            property.as_object_mut().unwrap().extend(variant.as_object().cloned().unwrap_or_default());
+        }
+    }
+    property
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON_DIR: &str = "../json";
+
+    #[test]
+    fn committed_type_definitions_are_up_to_date() {
+        for (path, generated) in type_definition_files().unwrap() {
+            let committed = fs::read_to_string(format!("{}/{}", JSON_DIR, path))
+                .unwrap_or_else(|err| panic!("{}: {}", path, err));
+            assert!(committed == generated, "json/{} is out of date, regenerate it with the schema command", path);
+        }
+    }
+
+    #[test]
+    fn committed_output_matches_committed_schemas() {
+        let schema_dir = format!("{}/schema/v{}", JSON_DIR, SCHEMA_VERSION);
+        let index: Value = serde_json::from_str(&fs::read_to_string(format!("{}/index.json", schema_dir)).unwrap()).unwrap();
+
+        for region in ["en", "jp"] {
+            for entry in index["files"].as_array().unwrap() {
+                let file = entry["file"].as_str().unwrap();
+                let content = fs::read_to_string(format!("{}/{}/{}", JSON_DIR, region, file))
+                    .unwrap_or_else(|err| panic!("json/{}/{}: {}", region, file, err));
+
+                let schema: Value = serde_json::from_str(
+                    &fs::read_to_string(format!("{}/{}", schema_dir, entry["schema"].as_str().unwrap())).unwrap(),
+                ).unwrap();
+                let validator = jsonschema::validator_for(&schema).unwrap();
+                let instance: Value = serde_json::from_str(&content).unwrap();
+
+                let errors: Vec<String> = validator.iter_errors(&instance)
+                    .take(10)
+                    .map(|err| format!("{} at {}", err, err.instance_path))
+                    .collect();
+                assert!(errors.is_empty(), "json/{}/{} does not match its schema:\n{}", region, file, errors.join("\n"));
+            }
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::{BTreeMap, HashMap}};
+
+use crate::{is_sentinel, names::name_key, unique_cards, Card};
+
+/// Prebuilt full-text index over the card names, types and effect text,
+/// one document per card number. Written next to cards.json so clients can
+/// search without building their own.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SearchIndex {
+    /// Indexed fields, referenced by position from `Posting`
+    pub fields: Vec<IndexField>,
+    /// Referenced by position from `Posting`
+    pub documents: Vec<IndexDocument>,
+    /// Normalized term to every document containing it
+    pub terms: BTreeMap<String, Vec<Posting>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IndexField {
+    pub name: String,
+    /// Score multiplier for matches in this field
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IndexDocument {
+    pub card_number: String,
+    pub card_name: String,
+}
+
+/// `[document, field, occurrences]`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub struct Posting(pub u32, pub u8, pub u32);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit<'a> {
+    pub card_number: &'a str,
+    pub card_name: &'a str,
+    pub score: f64,
+}
+
+const NAME_WEIGHT: f64 = 3.0;
+const TYPES_WEIGHT: f64 = 2.0;
+const EFFECTS_WEIGHT: f64 = 1.0;
+
+// How much a match counts depending on how close the term is to the query,
+// typo matches are scaled down further by the share of letters that differ
+const EXACT_MATCH: f64 = 1.0;
+const PREFIX_MATCH: f64 = 0.75;
+const TYPO_MATCH: f64 = 0.75;
+
+/// Lowercases, folds full-width characters to ASCII and turns punctuation
+/// and markup into spaces, so `Monkey.D.Luffy`, `Monkey D. Luffy` and
+/// `ＭＯＮＫＥＹ・Ｄ・ＬＵＦＦＹ` all become `monkey d luffy`
+pub fn normalize(text: &str) -> String {
+    let text = if text.contains('<') {
+        Regex::new(r"<[^>]*>").unwrap().replace_all(text, " ")
+    } else {
+        text.into()
+    };
+
+    let mut normalized = String::with_capacity(text.len());
+    for c in text.chars() {
+        let c = match c {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        };
+
+        if c.is_alphanumeric() {
+            normalized.extend(c.to_lowercase());
+        } else {
+            normalized.push(' ');
+        }
+    }
+
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    normalize(text).split(' ').filter(|token| !token.is_empty()).map(str::to_string).collect()
+}
+
+impl SearchIndex {
+    pub fn build(cards: &[Card]) -> SearchIndex {
+        let fields = vec![
+            IndexField { name: "card_name".to_string(), weight: NAME_WEIGHT },
+            IndexField { name: "types".to_string(), weight: TYPES_WEIGHT },
+            IndexField { name: "effects".to_string(), weight: EFFECTS_WEIGHT },
+        ];
+
+        let mut documents = Vec::new();
+        let mut terms: BTreeMap<String, Vec<Posting>> = BTreeMap::new();
+
+        for (document, card) in unique_cards(cards).into_iter().enumerate() {
+            documents.push(IndexDocument {
+                card_number: card.card_number.clone(),
+                card_name: card.card_name.clone(),
+            });
+
+            let types: Vec<&str> = card.types.iter().map(String::as_str).filter(|value| !is_sentinel(value)).collect();
+            let field_texts = [card.card_name.clone(), types.join(" "), card.effects.clone().unwrap_or_default()];
+
+            for (field, text) in field_texts.iter().enumerate() {
+                let mut counts: BTreeMap<String, u32> = BTreeMap::new();
+                for token in tokenize(text) {
+                    *counts.entry(token).or_default() += 1;
+                }
+
+                for (term, count) in counts {
+                    terms.entry(term).or_default().push(Posting(document as u32, field as u8, count));
+                }
+            }
+        }
+
+        SearchIndex { fields, documents, terms }
+    }
+
+    /// Ranked, typo tolerant search. Every query word has to match the card
+    /// exactly or, when it's not in the index, within one or two typos
+    /// depending on its length. The last word can also match as a prefix so
+    /// partially typed names work. An alias like `whitebeard` also finds the
+    /// card it stands for.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit<'_>> {
+        let mut hits = self.matching(query);
+
+        let query_key = name_key(query);
+        let printed_name = self.documents.iter()
+            .map(|document| document.card_name.as_str())
+            .find(|name| name_key(name) == query_key && normalize(name) != normalize(query));
+
+        if let Some(printed_name) = printed_name {
+            for hit in self.matching(printed_name) {
+                match hits.iter_mut().find(|existing| existing.card_number == hit.card_number) {
+                    Some(existing) => existing.score = existing.score.max(hit.score),
+                    None => hits.push(hit),
+                }
+            }
+        }
+
+        hits.sort_by(|a, b| {
+            b.score.partial_cmp(&a.score)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.card_number.cmp(b.card_number))
+        });
+        hits.truncate(limit);
+        hits
+    }
+
+    // Every card matching all the query words, unsorted
+    fn matching(&self, query: &str) -> Vec<SearchHit<'_>> {
+        let tokens = tokenize(query);
+        if tokens.is_empty() {
+            return Vec::new();
+        }
+
+        let document_count = self.documents.len() as f64;
+        let mut scores: HashMap<u32, (f64, usize)> = HashMap::new();
+
+        for (position, token) in tokens.iter().enumerate() {
+            let is_last = position == tokens.len() - 1;
+            // Words spelled right somewhere in the index aren't typos
+            let allow_typos = !self.terms.contains_key(token);
+
+            let candidates: Vec<(&Vec<Posting>, Match, f64)> = self.terms.iter()
+                .filter_map(|(term, postings)| {
+                    let quality = match_quality(token, term, is_last, allow_typos)?;
+                    let mut matching_documents: Vec<u32> = postings.iter().map(|posting| posting.0).collect();
+                    matching_documents.dedup();
+                    Some((postings, quality, (1.0 + document_count / matching_documents.len() as f64).ln()))
+                })
+                .collect();
+
+            // A typo is more likely meant as a common word than a rare one, so every
+            // correction gets the weight of the commonest instead of its own
+            let typo_idf = candidates.iter()
+                .filter(|(_, quality, _)| matches!(quality, Match::Typo(_)))
+                .map(|(_, _, idf)| *idf)
+                .reduce(f64::min);
+
+            // Best scoring term per document for this query word
+            let mut best: HashMap<u32, f64> = HashMap::new();
+
+            for (postings, quality, idf) in candidates {
+                let (quality, idf) = match quality {
+                    Match::Typo(quality) => (quality, typo_idf.unwrap_or(idf)),
+                    Match::Exact => (EXACT_MATCH, idf),
+                    Match::Prefix => (PREFIX_MATCH, idf),
+                };
+
+                let mut term_scores: HashMap<u32, f64> = HashMap::new();
+                for &Posting(document, field, count) in postings {
+                    let weight = self.fields.get(field as usize).map_or(1.0, |field| field.weight);
+                    *term_scores.entry(document).or_default() += quality * idf * weight * (1.0 + (count as f64).ln());
+                }
+
+                for (document, score) in term_scores {
+                    let entry = best.entry(document).or_default();
+                    *entry = entry.max(score);
+                }
+            }
+
+            for (document, score) in best {
+                let entry = scores.entry(document).or_default();
+                entry.0 += score;
+                entry.1 += 1;
+            }
+        }
+
+        let normalized_query = tokens.join(" ");
+        let query_key = name_key(query);
+        scores
+            .into_iter()
+            .filter(|(_, (_, matched))| *matched == tokens.len())
+            .filter_map(|(document, (score, _))| {
+                let document = self.documents.get(document as usize)?;
+
+                // Whole name (or alias) matches beat cards that just mention every word
+                let name = normalize(&document.card_name);
+                let score = if name == normalized_query || name_key(&document.card_name) == query_key {
+                    score * 2.0
+                } else if name.contains(&normalized_query) {
+                    score * 1.5
+                } else {
+                    score
+                };
+
+                Some(SearchHit {
+                    card_number: &document.card_number,
+                    card_name: &document.card_name,
+                    score,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Match {
+    Exact,
+    Prefix,
+    /// How much of the term survived the typos, from 0 to `TYPO_MATCH`
+    Typo(f64),
+}
+
+fn match_quality(token: &str, term: &str, allow_prefix: bool, allow_typos: bool) -> Option<Match> {
+    if token == term {
+        return Some(Match::Exact);
+    }
+
+    let token_length = token.chars().count();
+    if allow_prefix && token_length >= 2 && term.starts_with(token) {
+        return Some(Match::Prefix);
+    }
+
+    let max_typos = match token_length {
+        _ if !allow_typos => return None,
+        0..=3 => return None,
+        4..=7 => 1,
+        _ => 2,
+    };
+
+    let distance = edit_distance(token, term, max_typos)?;
+    let length = token_length.max(term.chars().count());
+    Some(Match::Typo(TYPO_MATCH * (1.0 - distance as f64 / length as f64)))
+}
+
+/// Levenshtein distance counting a swap of neighbouring letters as one edit,
+/// or None once it's known to be over `max`
+fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+
+    let mut before_previous: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        let mut current = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + substitution);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before_previous[j - 2] + 1);
+            }
+        }
+
+        if current.iter().min().is_some_and(|&lowest| lowest > max) {
+            return None;
+        }
+
+        before_previous = previous;
+        previous = current;
+    }
+
+    let distance = previous[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An index over just the card names
+    fn name_index(cards: &[(&str, &str)]) -> SearchIndex {
+        let mut terms: BTreeMap<String, Vec<Posting>> = BTreeMap::new();
+        for (document, (_, name)) in cards.iter().enumerate() {
+            for token in tokenize(name) {
+                terms.entry(token).or_default().push(Posting(document as u32, 0, 1));
+            }
+        }
+
+        SearchIndex {
+            fields: vec![IndexField { name: "card_name".to_string(), weight: NAME_WEIGHT }],
+            documents: cards.iter()
+                .map(|(card_number, name)| IndexDocument { card_number: card_number.to_string(), card_name: name.to_string() })
+                .collect(),
+            terms,
+        }
+    }
+
+    fn card_numbers<'a>(hits: &[SearchHit<'a>]) -> Vec<&'a str> {
+        hits.iter().map(|hit| hit.card_number).collect()
+    }
+
+    #[test]
+    fn edit_distance_counts_edits_up_to_max() {
+        assert_eq!(edit_distance("luffy", "luffy", 2), Some(0));
+        assert_eq!(edit_distance("lufy", "luffy", 2), Some(1));
+        assert_eq!(edit_distance("lufy", "lucy", 2), Some(1));
+        assert_eq!(edit_distance("zoro", "zorro", 1), Some(1));
+        assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance("kitten", "sitting", 2), None);
+        assert_eq!(edit_distance("ace", "acehigh", 2), None);
+    }
+
+    #[test]
+    fn neighbouring_swap_is_one_edit() {
+        assert_eq!(edit_distance("lfufy", "luffy", 2), Some(1));
+        assert_eq!(edit_distance("nmai", "nami", 1), Some(1));
+    }
+
+    #[test]
+    fn prefix_only_for_the_last_word() {
+        assert_eq!(match_quality("kata", "katakuri", true, true), Some(Match::Prefix));
+        assert_eq!(match_quality("kata", "katakuri", false, true), None);
+        assert_eq!(match_quality("k", "katakuri", true, true), None);
+        assert_eq!(match_quality("katakuri", "katakuri", true, true), Some(Match::Exact));
+    }
+
+    #[test]
+    fn typos_scale_with_length_and_distance() {
+        assert_eq!(match_quality("lufy", "luffy", false, false), None);
+        assert_eq!(match_quality("ace", "acc", false, true), None);
+
+        let quality = |token, term| match match_quality(token, term, false, true) {
+            Some(Match::Typo(quality)) => quality,
+            other => panic!("{:?}", other),
+        };
+        assert!(quality("lufy", "luffy") > quality("lufy", "lucy"));
+        assert!(quality("doflamingo", "doflamingo".replace("fl", "").as_str()) < quality("doflamingo", "doflaminga"));
+    }
+
+    #[test]
+    fn typo_prefers_the_common_correction() {
+        let index = name_index(&[
+            ("OP07-112", "Lucy"),
+            ("OP01-003", "Monkey.D.Luffy"),
+            ("OP01-024", "Monkey.D.Luffy"),
+            ("ST01-001", "Monkey.D.Luffy"),
+        ]);
+
+        assert_eq!(card_numbers(&index.search("lufy", 10)), vec!["OP01-003", "OP01-024", "ST01-001", "OP07-112"]);
+    }
+
+    #[test]
+    fn exact_name_and_alias_hits_rank_first() {
+        let index = name_index(&[
+            ("OP01-001", "Roronoa Zoro"),
+            ("OP06-118", "Roronoa Zoro & Sanji"),
+            ("OP02-004", "Edward.Newgate"),
+            ("OP09-061", "Whitebeard Pirates Flag"),
+        ]);
+
+        assert_eq!(card_numbers(&index.search("roronoa zoro", 10)), vec!["OP01-001", "OP06-118"]);
+        assert_eq!(card_numbers(&index.search("whitebeard", 10)), vec!["OP02-004", "OP09-061"]);
+        assert_eq!(card_numbers(&index.search("newgate", 10)), vec!["OP02-004"]);
+    }
+
+    #[test]
+    fn last_word_matches_as_prefix() {
+        let index = name_index(&[("OP03-099", "Charlotte Katakuri"), ("OP04-033", "Charlotte Pudding")]);
+
+        assert_eq!(card_numbers(&index.search("charlotte kata", 10)), vec!["OP03-099"]);
+        assert_eq!(card_numbers(&index.search("kata charlotte", 10)), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,308 @@
+use axum::{
+    extract::{rejection::QueryRejection, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{query::Query as SearchQuery, generate_filters, label, parse_number, set_key, unique_cards, Card};
+
+const DEFAULT_PER_PAGE: usize = 50;
+const MAX_PER_PAGE: usize = 500;
+
+struct AppState {
+    cards: Vec<Card>,
+}
+
+/// Query parameters for `/cards`, one per facet in `generate_filters`.
+/// List values are comma separated and match if any of them match.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CardFilter {
+    color: Option<String>,
+    card_type: Option<String>,
+    rarity: Option<String>,
+    attribute: Option<String>,
+    #[serde(rename = "trait")]
+    card_trait: Option<String>,
+    keyword: Option<String>,
+    set: Option<String>,
+    block_icon: Option<String>,
+    has_trigger: Option<bool>,
+    cost_min: Option<u32>,
+    cost_max: Option<u32>,
+    power_min: Option<u32>,
+    power_max: Option<u32>,
+    counter_min: Option<u32>,
+    counter_max: Option<u32>,
+    life_min: Option<u32>,
+    life_max: Option<u32>,
+    /// Free text, matched against the name, effects and trigger
+    q: Option<String>,
+    /// Search syntax, e.g. `c:red cost<=4 kw:blocker`, see `query::Query`
+    query: Option<String>,
+    /// Only one printing per card number
+    unique: bool,
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+impl CardFilter {
+    pub fn matches(&self, card: &Card) -> bool {
+        any_of(&self.color, card.colors.iter().map(label))
+            && any_of(&self.card_type, [label(&card.card_type)])
+            && any_of(&self.rarity, [label(&card.rarity)])
+            && any_of(&self.attribute, card.attributes.iter().cloned())
+            && any_of(&self.card_trait, card.types.iter().cloned())
+            && any_of(&self.keyword, card.card_effects.iter().map(|effect| effect.trim_matches(['[', ']']).to_string()))
+            && any_of(&self.set, set_key(&card.card_sets))
+            && any_of(&self.block_icon, [card.block_icon.clone()])
+            && self.has_trigger.is_none_or(|has_trigger| card.has_trigger == has_trigger)
+            && in_range(parse_number(&card.cost), self.cost_min, self.cost_max)
+            && in_range(parse_number(&card.power), self.power_min, self.power_max)
+            && in_range(parse_number(&card.counter), self.counter_min, self.counter_max)
+            && in_range(parse_number(&card.life), self.life_min, self.life_max)
+            && self.q.as_deref().is_none_or(|text| matches_text(card, text))
+    }
+}
+
+// Case insensitive, any of the comma separated wanted values
+fn any_of(wanted: &Option<String>, values: impl IntoIterator<Item = String>) -> bool {
+    let Some(wanted) = wanted else {
+        return true;
+    };
+
+    let wanted: Vec<String> = wanted.split(',').map(|value| value.trim().to_lowercase()).collect();
+    values.into_iter().any(|value| wanted.contains(&value.to_lowercase()))
+}
+
+fn in_range(value: Option<u32>, min: Option<u32>, max: Option<u32>) -> bool {
+    if min.is_none() && max.is_none() {
+        return true;
+    }
+
+    value.is_some_and(|value| min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max))
+}
+
+fn matches_text(card: &Card, text: &str) -> bool {
+    let text = text.to_lowercase();
+    [Some(&card.card_name), card.effects.as_ref(), card.trigger.as_ref()]
+        .into_iter()
+        .flatten()
+        .any(|field| field.to_lowercase().contains(&text))
+}
+
+#[derive(Debug, Serialize)]
+struct Page<T> {
+    page: usize,
+    per_page: usize,
+    total: usize,
+    total_pages: usize,
+    data: Vec<T>,
+}
+
+fn paginate<T: Clone>(items: &[T], page: Option<usize>, per_page: Option<usize>) -> Page<T> {
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    let page = page.unwrap_or(1).max(1);
+    let start = page.saturating_sub(1).saturating_mul(per_page).min(items.len());
+    let end = start.saturating_add(per_page).min(items.len());
+
+    Page {
+        page,
+        per_page,
+        total: items.len(),
+        total_pages: items.len().div_ceil(per_page),
+        data: items[start..end].to_vec(),
+    }
+}
+
+fn not_found(message: String) -> Response {
+    (StatusCode::NOT_FOUND, Json(json!({ "error": message }))).into_response()
+}
+
+fn bad_request(message: String) -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({ "error": message }))).into_response()
+}
+
+async fn list_cards(State(state): State<Arc<AppState>>, filter: Result<Query<CardFilter>, QueryRejection>) -> Response {
+    let filter = match filter {
+        Ok(Query(filter)) => filter,
+        Err(rejection) => return bad_request(rejection.body_text()),
+    };
+
+    let search = match filter.query.as_deref().map(SearchQuery::parse).transpose() {
+        Ok(search) => search,
+        Err(error) => return bad_request(format!("Invalid query: {}", error)),
+    };
+
+    let candidates: Vec<&Card> = if filter.unique {
+        unique_cards(&state.cards)
+    } else {
+        state.cards.iter().collect()
+    };
+
+    // Only references, the page gets serialized straight from the loaded cards
+    let matching: Vec<&Card> = candidates
+        .into_iter()
+        .filter(|card| filter.matches(card))
+        .filter(|card| search.as_ref().is_none_or(|search| search.matches(card)))
+        .collect();
+
+    Json(paginate(&matching, filter.page, filter.per_page)).into_response()
+}
+
+async fn get_card(State(state): State<Arc<AppState>>, Path(card_number): Path<String>) -> Response {
+    let printings: Vec<&Card> = state.cards.iter()
+        .filter(|card| card.card_number.eq_ignore_ascii_case(&card_number))
+        .collect();
+
+    if printings.is_empty() {
+        return not_found(format!("Unknown card number: {}", card_number));
+    }
+
+    Json(json!({
+        "card_number": printings[0].card_number,
+        "card_name": printings[0].card_name,
+        "printings": printings,
+    })).into_response()
+}
+
+async fn list_sets(State(state): State<Arc<AppState>>) -> Json<serde_json::Value> {
+    let mut sets: BTreeMap<String, (&str, usize)> = BTreeMap::new();
+    for card in &state.cards {
+        if let Some(key) = set_key(&card.card_sets) {
+            sets.entry(key).or_insert((&card.card_sets, 0)).1 += 1;
+        }
+    }
+
+    Json(json!(sets.into_iter()
+        .map(|(key, (name, card_count))| json!({ "key": key, "name": name, "card_count": card_count }))
+        .collect::<Vec<_>>()))
+}
+
+async fn filters(State(state): State<Arc<AppState>>) -> Response {
+    Json(generate_filters(&state.cards)).into_response()
+}
+
+pub fn router(cards: Vec<Card>) -> Router {
+    let state = Arc::new(AppState { cards });
+
+    Router::new()
+        .route("/cards", get(list_cards))
+        .route("/cards/{card_number}", get(get_card))
+        .route("/sets", get(list_sets))
+        .route("/filters", get(filters))
+        .with_state(state)
+}
+
+/// Serves the card list over HTTP until the process is stopped
+pub async fn serve(cards: Vec<Card>, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port)).await?;
+    println!("Serving {} cards on http://{}", cards.len(), listener.local_addr()?);
+    axum::serve(listener, router(cards)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_card;
+    use axum::{body::Body, http::Request};
+    use http_body_util::BodyExt;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    fn cards() -> Vec<Card> {
+        vec![
+            test_card(json!({ "card_number": "OP01-001", "card_name": "Roronoa Zoro", "card_type": "LEADER", "life": "5", "cost": "-" })),
+            test_card(json!({ "card_number": "OP01-016", "card_name": "Nami", "cost": "1" })),
+            test_card(json!({ "card_number": "OP01-016", "card_name": "Nami", "cost": "1", "image_name": "OP01-016_p1", "card_sets": "-PROMO-" })),
+            test_card(json!({ "card_number": "OP01-025", "card_name": "Roronoa Zoro", "cost": "3", "colors": ["Green"] })),
+            test_card(json!({ "card_number": "OP02-001", "card_name": "Edward.Newgate", "card_type": "LEADER", "card_sets": "-PARAMOUNT WAR- [OP02]" })),
+        ]
+    }
+
+    async fn get(uri: &str) -> (StatusCode, Value) {
+        let response = router(cards())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn card_numbers(page: &Value) -> Vec<&str> {
+        page["data"].as_array().unwrap().iter().map(|card| card["card_number"].as_str().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn cards_are_filtered_and_paginated() {
+        let (status, page) = get("/cards?per_page=2&page=2").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!((page["page"].as_u64(), page["per_page"].as_u64(), page["total"].as_u64(), page["total_pages"].as_u64()), (Some(2), Some(2), Some(5), Some(3)));
+        assert_eq!(card_numbers(&page), ["OP01-016", "OP01-025"]);
+
+        let (_, page) = get("/cards?card_type=character&cost_max=2&unique=true").await;
+        assert_eq!(card_numbers(&page), ["OP01-016"]);
+
+        let (_, page) = get("/cards?color=green,blue").await;
+        assert_eq!(card_numbers(&page), ["OP01-025"]);
+
+        let (_, page) = get("/cards?query=t:leader").await;
+        assert_eq!(card_numbers(&page), ["OP01-001", "OP02-001"]);
+    }
+
+    #[tokio::test]
+    async fn huge_pages_are_empty_instead_of_overflowing() {
+        let (status, page) = get(&format!("/cards?page={}&per_page=500", usize::MAX)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(page["total"], 5);
+        assert!(card_numbers(&page).is_empty());
+
+        // Out of range sizes are clamped
+        let (_, page) = get("/cards?per_page=0&page=0").await;
+        assert_eq!((page["page"].as_u64(), page["per_page"].as_u64()), (Some(1), Some(1)));
+    }
+
+    #[tokio::test]
+    async fn bad_query_params_are_json_errors() {
+        let (status, body) = get("/cards?cost_max=lots").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().contains("cost_max"));
+
+        let (status, body) = get("/cards?query=is:shiny").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().starts_with("Invalid query"));
+    }
+
+    #[tokio::test]
+    async fn a_card_lists_its_printings() {
+        let (status, card) = get("/cards/op01-016").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(card["card_name"], "Nami");
+        assert_eq!(card["printings"].as_array().unwrap().len(), 2);
+
+        let (status, body) = get("/cards/OP99-001").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "Unknown card number: OP99-001");
+    }
+
+    #[tokio::test]
+    async fn sets_and_filters() {
+        let (status, sets) = get("/sets").await;
+        assert_eq!(status, StatusCode::OK);
+        let sets: Vec<(&str, u64)> = sets.as_array().unwrap().iter()
+            .map(|set| (set["key"].as_str().unwrap(), set["card_count"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(sets, [("paramount-war-op02", 1), ("promo", 1), ("romance-dawn-op01", 3)]);
+
+        let (status, filters) = get("/filters").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(filters["ranges"]["life"], json!({ "min": 5, "max": 5 }));
+    }
+}
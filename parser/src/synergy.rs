@@ -0,0 +1,158 @@
+use serde::Serialize;
+use std::cmp::Ordering;
+
+use crate::{is_sentinel, label, names::name_key, parse_number, unique_cards, Card, CardType, Color};
+
+// A trait the Leader's effect asks for counts more than one it just shares
+const REFERENCED_TYPE_SCORE: u32 = 3;
+const REFERENCED_NAME_SCORE: u32 = 3;
+const SHARED_TYPE_SCORE: u32 = 1;
+const REFERENCES_LEADER_TYPE_SCORE: u32 = 1;
+
+/// Cards that fit a Leader, for a content starting point on every new Leader
+#[derive(Debug, Serialize)]
+pub struct LeaderReport<'a> {
+    pub card_number: &'a str,
+    pub card_name: &'a str,
+    pub colors: &'a [Color],
+    pub types: Vec<&'a str>,
+    /// Traits the Leader's effect refers to, e.g. {Straw Hat Crew}
+    pub referenced_types: &'a [String],
+    pub referenced_names: &'a [String],
+    /// Cards that share a color with the Leader, with or without synergy
+    pub color_match_count: usize,
+    /// Every color matching card, best first
+    pub synergies: Vec<Synergy<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Synergy<'a> {
+    pub card_number: &'a str,
+    pub card_name: &'a str,
+    pub card_type: String,
+    pub colors: &'a [Color],
+    pub cost: Option<u32>,
+    pub score: u32,
+    /// The card's traits that the Leader's effect refers to
+    pub referenced_types: Vec<&'a str>,
+    /// The card's traits the Leader also has
+    pub shared_types: Vec<&'a str>,
+    /// The Leader's effect refers to this card by name
+    pub named_by_leader: bool,
+    /// Leader traits this card's effect refers to
+    pub references_leader_types: Vec<&'a str>,
+}
+
+/// Every Leader with its report
+pub fn leader_reports(cards: &[Card]) -> Vec<LeaderReport<'_>> {
+    let unique = unique_cards(cards);
+
+    unique.iter()
+        .filter(|card| card.card_type == CardType::LEADER)
+        .map(|leader| leader_report(leader, &unique))
+        .collect()
+}
+
+pub fn leader_report<'a>(leader: &'a Card, cards: &[&'a Card]) -> LeaderReport<'a> {
+    let leader_types: Vec<&str> = leader.types.iter()
+        .map(String::as_str)
+        .filter(|value| !is_sentinel(value))
+        .collect();
+
+    let color_matches: Vec<&Card> = cards.iter()
+        .copied()
+        .filter(|card| !matches!(card.card_type, CardType::LEADER | CardType::DON))
+        .filter(|card| card.colors.iter().any(|color| leader.colors.contains(color)))
+        .collect();
+
+    let mut synergies: Vec<Synergy> = color_matches.iter()
+        .map(|card| {
+            let card_types = || card.types.iter().map(String::as_str).filter(|value| !is_sentinel(value));
+
+            let referenced_types: Vec<&str> = card_types().filter(|value| leader.referenced_types.iter().any(|referenced| referenced == value)).collect();
+            let shared_types: Vec<&str> = card_types().filter(|value| leader_types.contains(value)).collect();
+            let named_by_leader = leader.referenced_names.iter().any(|name| name_key(name) == card.name_key);
+            let references_leader_types: Vec<&str> = card.referenced_types.iter()
+                .map(String::as_str)
+                .filter(|value| leader_types.contains(value))
+                .collect();
+
+            let score = referenced_types.len() as u32 * REFERENCED_TYPE_SCORE
+                + shared_types.len() as u32 * SHARED_TYPE_SCORE
+                + u32::from(named_by_leader) * REFERENCED_NAME_SCORE
+                + references_leader_types.len() as u32 * REFERENCES_LEADER_TYPE_SCORE;
+
+            Synergy {
+                card_number: &card.card_number,
+                card_name: &card.card_name,
+                card_type: label(&card.card_type),
+                colors: &card.colors,
+                cost: parse_number(&card.cost),
+                score,
+                referenced_types,
+                shared_types,
+                named_by_leader,
+                references_leader_types,
+            }
+        })
+        .collect();
+
+    synergies.sort_by(|a, b| match b.score.cmp(&a.score) {
+        Ordering::Equal => a.card_number.cmp(b.card_number),
+        ordering => ordering,
+    });
+
+    LeaderReport {
+        card_number: &leader.card_number,
+        card_name: &leader.card_name,
+        colors: &leader.colors,
+        types: leader_types,
+        referenced_types: &leader.referenced_types,
+        referenced_names: &leader.referenced_names,
+        color_match_count: color_matches.len(),
+        synergies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_card;
+    use serde_json::json;
+
+    fn cards() -> Vec<Card> {
+        vec![
+            test_card(json!({
+                "card_number": "OP01-001",
+                "card_name": "Monkey.D.Luffy",
+                "card_type": "LEADER",
+                "types": ["Straw Hat Crew", "Supernovas"],
+                "referenced_types": ["Straw Hat Crew"],
+                "referenced_names": ["Nami"],
+            })),
+            test_card(json!({ "card_number": "OP01-016", "card_name": "Nami", "name_key": name_key("Nami") })),
+            test_card(json!({ "card_number": "OP01-024", "card_name": "Monkey.D.Luffy", "types": ["Supernovas"] })),
+            test_card(json!({ "card_number": "OP01-006", "card_name": "Otama", "types": ["Land of Wano"], "referenced_types": ["Straw Hat Crew"] })),
+            test_card(json!({ "card_number": "OP01-004", "card_name": "Usopp", "types": ["Land of Wano"] })),
+            test_card(json!({ "card_number": "OP01-025", "card_name": "Roronoa Zoro", "colors": ["Green"] })),
+        ]
+    }
+
+    #[test]
+    fn scores_every_color_matching_card() {
+        let cards = cards();
+        let reports = leader_reports(&cards);
+        assert_eq!(reports.len(), 1);
+
+        let report = &reports[0];
+        assert_eq!(report.color_match_count, 4);
+
+        let scores: Vec<(&str, u32)> = report.synergies.iter().map(|synergy| (synergy.card_number, synergy.score)).collect();
+        assert_eq!(scores, [("OP01-016", 7), ("OP01-006", 1), ("OP01-024", 1), ("OP01-004", 0)]);
+
+        let nami = &report.synergies[0];
+        assert_eq!((nami.referenced_types.as_slice(), nami.shared_types.as_slice(), nami.named_by_leader), (&["Straw Hat Crew"][..], &["Straw Hat Crew"][..], true));
+        assert_eq!(report.synergies[1].references_leader_types, ["Straw Hat Crew"]);
+        assert_eq!(report.synergies[2].shared_types, ["Supernovas"]);
+    }
+}
@@ -11,6 +11,7 @@ import { resolve } from "path";
 
 interface Card {
     image_url: string;
+    official_image_url?: string;
     image_name: string;
 }
 
@@ -155,7 +156,8 @@ async function main(): Promise<void> {
             limit(async () => {
                 const key = `${lang}/${card.image_name}.webp`;
                 try {
-                    const png = await downloadImage(card.image_url);
+                    // image_url can point at our own CDN, always pull from the official site
+                    const png = await downloadImage(card.official_image_url || card.image_url);
                     const webp = await convertToWebp(png);
 
                     await uploadToR2(client, bucket, key, webp);